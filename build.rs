@@ -45,6 +45,7 @@ fn main() {
     
     for file in &files {
       let contents = fs::read_to_string(Path::new(format!("lib/src/{}", file).as_str())).unwrap();
+      let contents = patch_line_search(file, contents);
       let contents = replacements.iter().fold(contents, |acc, &r| acc.replace(r, format!("{}_{}", r, i).as_str()));
       let out_dir_file = PathBuf::from(out_dir.clone()).join(file);
       fs::write(out_dir_file.clone(), contents).expect("Couldn't write");
@@ -99,4 +100,35 @@ fn main() {
   let lib_file = PathBuf::from(out_dir.clone()).join("lib.rs");
   fs::write(lib_file.clone(), lib).expect("Couldn't write lib file");
 }
+
+// The Moré-Thuente tolerances and the limit of 20 line search steps are
+// constants in lnsrlb and mainlb. Turn them into globals, named to be
+// renamed with setulb, so each instance can be set from `LbfgsbParameter`.
+fn patch_line_search(file: &str, contents: String) -> String {
+  let include = "#include \"lbfgsb.h\"\n";
+  match file {
+    "linesearch.c" => {
+      let globals = format!(
+        "{include}double setulb_ftol = FTOL;\ndouble setulb_gtol = GTOL;\ndouble setulb_xtol = XTOL;\ninteger setulb_maxls = 20;\n"
+      );
+      let contents = replace_once(file, contents, include, &globals);
+      let contents = replace_once(file, contents, "static double c_b14 = FTOL;", "double c_b14 = setulb_ftol;");
+      let contents = replace_once(file, contents, "static double c_b15 = GTOL;", "double c_b15 = setulb_gtol;");
+      replace_once(file, contents, "static double c_b16 = XTOL;", "double c_b16 = setulb_xtol;")
+    }
+    "lbfgsb.c" => {
+      let contents = replace_once(file, contents, include, &format!("{include}extern integer setulb_maxls;\n"));
+      replace_once(file, contents, "iback >= 20", "iback >= setulb_maxls")
+    }
+    _ => contents,
+  }
+}
+
+// Replace `from`, which must occur exactly once, so that a change of the C
+// sources cannot silently leave the line search parameters unused.
+fn replace_once(file: &str, contents: String, from: &str, to: &str) -> String {
+  let count = contents.matches(from).count();
+  assert!(count == 1, "expected {from:?} once in {file}, found {count} times");
+  contents.replacen(from, to, 1)
+}
 // build.rs:1 ends here
@@ -0,0 +1,69 @@
+// [[file:../lbfgsb.note::*best][best:1]]
+/// The best (lowest f) feasible point evaluated during a minimization.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BestPoint {
+  pub x: Vec<f64>,
+  pub f: f64,
+  pub g: Vec<f64>,
+}
+
+impl BestPoint {
+  /// Copy the point into x, f and g.
+  pub fn restore(&self, x: &mut [f64], f: &mut f64, g: &mut [f64]) {
+    x.copy_from_slice(&self.x);
+    *f = self.f;
+    g.copy_from_slice(&self.g);
+  }
+}
+
+/// Keeps track of the best point evaluated so far.
+#[derive(Default)]
+pub(crate) struct BestTracker {
+  best: Option<BestPoint>,
+}
+
+impl BestTracker {
+  /// Record the evaluation of f and g at x if both are finite, x is within
+  /// the bounds and f is lower than any value seen before.
+  pub fn update(&mut self, x: &[f64], f: f64, g: &[f64], l: &[f64], u: &[f64], nbd: &[i64]) {
+    if !f.is_finite() || self.best.as_ref().is_some_and(|b| f >= b.f) {
+      return;
+    }
+    if !g.iter().all(|gi| gi.is_finite()) {
+      return;
+    }
+    let feasible = (0..x.len()).all(|i| match nbd[i] {
+      1 => x[i] >= l[i],
+      2 => x[i] >= l[i] && x[i] <= u[i],
+      3 => x[i] <= u[i],
+      _ => true,
+    });
+    if !feasible {
+      return;
+    }
+
+    match &mut self.best {
+      Some(best) => {
+        best.x.copy_from_slice(x);
+        best.f = f;
+        best.g.copy_from_slice(g);
+      }
+      None => {
+        self.best = Some(BestPoint {
+          x: x.to_vec(),
+          f,
+          g: g.to_vec(),
+        })
+      }
+    }
+  }
+
+  pub fn best(&self) -> Option<&BestPoint> {
+    self.best.as_ref()
+  }
+
+  pub fn into_best(self) -> Option<BestPoint> {
+    self.best
+  }
+}
+// best:1 ends here
@@ -0,0 +1,116 @@
+// [[file:../lbfgsb.note::*block][block:1]]
+use anyhow::{ensure, Result};
+
+use crate::kkt::projected_gradient_norm;
+use crate::memory::CorrectionPairs;
+use crate::objective::Objective;
+use crate::shared::{LbfgsbParameter, LbfgsbProblem};
+use crate::subset::Selection;
+
+/// Block-coordinate minimization: one cycle minimizes over each group of
+/// variables in turn with the others held fixed, until the projected
+/// gradient over all variables is small. Variables in no block are part of
+/// this criterion too, so a point where they are not stationary does not
+/// count as converged.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockCoordinate {
+  /// The indices of the variables of each block. Variables in no block
+  /// keep their values.
+  pub blocks: Vec<Vec<usize>>,
+  /// Stop when the infinity norm of the projected gradient, at the start or
+  /// at the end of a cycle, is at most pgtol.
+  pub pgtol: f64,
+  /// The maximum number of cycles over all blocks.
+  pub max_cycles: usize,
+}
+
+impl BlockCoordinate {
+  /// Cycle over `blocks` with pgtol = 1e-5 and at most 100 cycles.
+  pub fn new(blocks: Vec<Vec<usize>>) -> Self {
+    Self {
+      blocks,
+      pgtol: 1E-5,
+      max_cycles: 100,
+    }
+  }
+}
+
+/// Summary of a block-coordinate minimization.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BlockResult {
+  /// The number of cycles done.
+  pub ncycle: usize,
+  /// The infinity norm of the projected gradient over all variables after
+  /// the last cycle, or at the initial x if no cycle was done.
+  pub pgnorm: f64,
+  /// Whether pgnorm reached `BlockCoordinate::pgtol`.
+  pub converged: bool,
+  /// The number of iterations, summed over all block runs.
+  pub niter: usize,
+  /// The number of f and g evaluations, summed over all block runs and
+  /// including one evaluation of the full gradient at the initial x and one
+  /// after each cycle.
+  pub nfg: usize,
+}
+
+/// Minimize `problem` over the blocks of `blocks`, each run with the
+/// parameters `param`. A block is warm started with the correction pairs of
+/// its previous run. The final x, f(x) and g(x) are left in `problem`;
+/// `problem.select` and `problem.warm_start` are restored on return.
+pub fn block_coordinate<E: Objective>(
+  problem: &mut LbfgsbProblem<E>,
+  param: &LbfgsbParameter,
+  blocks: &BlockCoordinate,
+) -> Result<BlockResult> {
+  let (select, warm_start) = (problem.select.take(), problem.warm_start.take());
+  let result = cycle(problem, param, blocks);
+  problem.select = select;
+  problem.warm_start = warm_start;
+  result
+}
+
+fn cycle<E: Objective>(
+  problem: &mut LbfgsbProblem<E>,
+  param: &LbfgsbParameter,
+  blocks: &BlockCoordinate,
+) -> Result<BlockResult> {
+  let n = problem.x.len();
+  ensure!(!blocks.blocks.is_empty(), "no blocks");
+  for &i in blocks.blocks.iter().flatten() {
+    ensure!(i < n, "variable index {i} out of range for {n} variables");
+  }
+
+  let mut memory: Vec<Option<CorrectionPairs>> = vec![None; blocks.blocks.len()];
+  let mut summary = BlockResult {
+    pgnorm: evaluate(problem)?,
+    nfg: 1,
+    ..Default::default()
+  };
+  while summary.pgnorm > blocks.pgtol && summary.ncycle < blocks.max_cycles {
+    for (block, pairs) in blocks.blocks.iter().zip(&mut memory) {
+      problem.select = Some(Selection::Indices(block.clone()));
+      problem.warm_start = pairs.take();
+      let result = crate::router::lbfgsb(problem, param)?;
+      summary.niter += result.niter;
+      summary.nfg += result.nfg;
+      *pairs = Some(result.memory);
+    }
+    summary.ncycle += 1;
+    summary.pgnorm = evaluate(problem)?;
+    summary.nfg += 1;
+  }
+  summary.converged = summary.pgnorm <= blocks.pgtol;
+  Ok(summary)
+}
+
+// Evaluate f and the full g at x and return the projected gradient norm. A
+// block run may leave g of the held variables from another point, e.g. a
+// failed line search or a point other than the best one.
+fn evaluate<E: Objective>(problem: &mut LbfgsbProblem<E>) -> Result<f64> {
+  problem.f = problem.eval_fn.evaluate(&problem.x, &mut problem.g)?;
+  if let Some(aux) = problem.eval_fn.aux() {
+    problem.aux = Some(aux);
+  }
+  Ok(projected_gradient_norm(&problem.x, &problem.g, &problem.l, &problem.u, &problem.nbd))
+}
+// block:1 ends here
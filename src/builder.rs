@@ -0,0 +1,118 @@
+// [[file:../lbfgsb.note::*builder][builder:1]]
+use anyhow::{ensure, Result};
+
+use crate::shared::LbfgsbParameter;
+
+/// Builds `LbfgsbParameter` from the option names of
+/// `scipy.optimize.minimize(method="L-BFGS-B")`.
+///
+/// Note that scipy's `ftol` and `gtol` are the stopping tolerances `factr`
+/// and `pgtol`, not the line search tolerances of the same names in
+/// `LbfgsbParameter`.
+#[derive(Debug, Clone)]
+pub struct ParameterBuilder {
+  param: LbfgsbParameter,
+}
+
+impl Default for ParameterBuilder {
+  fn default() -> Self {
+    Self::scipy()
+  }
+}
+
+impl ParameterBuilder {
+  /// The defaults of scipy: maxcor = 10, ftol = 2.22e-9 (factr = 1e7),
+  /// gtol = 1e-5, maxiter = maxfun = 15000 and maxls = 20.
+  pub fn scipy() -> Self {
+    let mut param = LbfgsbParameter {
+      m: 10,
+      factr: 1E7,
+      pgtol: 1E-5,
+      maxls: 20,
+      ..Default::default()
+    };
+    param.stop.max_iter = Some(15000);
+    param.stop.max_fun = Some(15000);
+    Self { param }
+  }
+
+  /// Tight tolerances for accurate solutions: maxcor = 20, factr = 10,
+  /// gtol = 1e-10.
+  pub fn high_accuracy() -> Self {
+    Self::scipy().maxcor(20).factr(1E1).gtol(1E-10)
+  }
+
+  /// Loose tolerances for a rough solution: maxcor = 5, factr = 1e12,
+  /// gtol = 1e-3 and at most 1000 iterations.
+  pub fn quick() -> Self {
+    Self::scipy().maxcor(5).factr(1E12).gtol(1E-3).maxiter(1000)
+  }
+
+  /// Stop when the relative reduction of f is at most ftol, i.e.
+  /// factr = ftol / epsmch.
+  pub fn ftol(self, ftol: f64) -> Self {
+    self.factr(ftol / f64::EPSILON)
+  }
+
+  /// Set `factr` directly.
+  pub fn factr(mut self, factr: f64) -> Self {
+    self.param.factr = factr;
+    self
+  }
+
+  /// Stop when the projected gradient is at most gtol, i.e. pgtol.
+  pub fn gtol(mut self, gtol: f64) -> Self {
+    self.param.pgtol = gtol;
+    self
+  }
+
+  /// The number of corrections m of the limited memory matrix.
+  pub fn maxcor(mut self, maxcor: usize) -> Self {
+    self.param.m = maxcor;
+    self
+  }
+
+  /// The maximum number of iterations.
+  pub fn maxiter(mut self, maxiter: usize) -> Self {
+    self.param.stop.max_iter = Some(maxiter);
+    self
+  }
+
+  /// The maximum number of f and g evaluations.
+  pub fn maxfun(mut self, maxfun: usize) -> Self {
+    self.param.stop.max_fun = Some(maxfun);
+    self
+  }
+
+  /// The maximum number of evaluations in one line search.
+  pub fn maxls(mut self, maxls: usize) -> Self {
+    self.param.maxls = maxls;
+    self
+  }
+
+  /// Print level of setulb, see `LbfgsbParameter::iprint`.
+  pub fn iprint(mut self, iprint: i64) -> Self {
+    self.param.iprint = iprint;
+    self
+  }
+
+  /// Validate and return the parameters.
+  pub fn build(self) -> Result<LbfgsbParameter> {
+    let param = self.param;
+    ensure!(param.m > 0, "maxcor must be positive");
+    ensure!(param.factr >= 0.0 && param.factr.is_finite(), "invalid ftol: factr = {}", param.factr);
+    ensure!(param.pgtol >= 0.0 && param.pgtol.is_finite(), "invalid gtol: {}", param.pgtol);
+    ensure!(param.stop.max_iter != Some(0), "maxiter must be positive");
+    ensure!(param.stop.max_fun != Some(0), "maxfun must be positive");
+    ensure!(param.maxls > 0, "maxls must be positive");
+    Ok(param)
+  }
+}
+
+impl LbfgsbParameter {
+  /// Start building parameters from the scipy defaults.
+  pub fn builder() -> ParameterBuilder {
+    ParameterBuilder::scipy()
+  }
+}
+// builder:1 ends here
@@ -0,0 +1,75 @@
+// [[file:../lbfgsb.note::*cache][cache:1]]
+use std::collections::VecDeque;
+
+use anyhow::Result;
+
+use crate::objective::Objective;
+
+/// Hit/miss statistics of the evaluation cache.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+  /// Number of evaluations answered from the cache.
+  pub hits: usize,
+  /// Number of evaluations passed on to the objective.
+  pub misses: usize,
+}
+
+struct CacheEntry {
+  key: Vec<u64>,
+  f: f64,
+  g: Vec<f64>,
+}
+
+/// Remembers f(x) and g(x) of the last `capacity` evaluated points.
+///
+/// Points are compared by the exact bits of x, so only repeated requests for
+/// the very same point are answered from the cache.
+pub(crate) struct EvalCache {
+  capacity: usize,
+  entries: VecDeque<CacheEntry>,
+  stats: CacheStats,
+}
+
+impl EvalCache {
+  pub fn new(capacity: usize) -> Self {
+    Self {
+      capacity,
+      entries: VecDeque::with_capacity(capacity),
+      stats: CacheStats::default(),
+    }
+  }
+
+  pub fn stats(&self) -> CacheStats {
+    self.stats
+  }
+
+  /// Evaluate `objective` at `x`, unless `x` is one of the remembered points.
+  ///
+  /// On a cache hit the objective is not called, so its `aux` data still
+  /// refers to the last point it actually evaluated.
+  pub fn evaluate<O: Objective>(&mut self, objective: &mut O, x: &[f64], g: &mut [f64]) -> Result<f64> {
+    let hit = self
+      .entries
+      .iter()
+      .find(|e| e.key.iter().zip(x).all(|(k, xi)| *k == xi.to_bits()));
+    if let Some(entry) = hit {
+      self.stats.hits += 1;
+      g.copy_from_slice(&entry.g);
+      return Ok(entry.f);
+    }
+
+    self.stats.misses += 1;
+    let f = objective.evaluate(x, g)?;
+    if self.entries.len() == self.capacity {
+      self.entries.pop_front();
+    }
+    self.entries.push_back(CacheEntry {
+      key: x.iter().map(|xi| xi.to_bits()).collect(),
+      f,
+      g: g.to_vec(),
+    });
+
+    Ok(f)
+  }
+}
+// cache:1 ends here
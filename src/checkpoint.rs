@@ -0,0 +1,280 @@
+// [[file:../lbfgsb.note::*checkpoint][checkpoint:1]]
+use std::borrow::Cow;
+
+use anyhow::{bail, Result};
+
+use crate::shared::LbfgsbParameter;
+use crate::stopping::StopHistory;
+use crate::workspace::Workspace;
+
+const MAGIC: &[u8; 8] = b"LBFGSBCK";
+const VERSION: u32 = 1;
+
+/// The complete state of a minimization at the start of an iteration: x,
+/// f(x), g(x), the bounds, the setulb working arrays and parameters, the
+/// driver counters and the history of the stopping criteria.
+///
+/// x, f and g are kept as seen by setulb, together with the variable and
+/// objective scaling. A checkpoint is taken from the observer
+/// with `Progress::checkpoint`, and a run is continued from it by setting
+/// `LbfgsbProblem::resume`. The
+/// resumed run requests the same points and reaches the same result as an
+/// uninterrupted one, on any router instance.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Checkpoint {
+  pub(crate) x: Vec<f64>,
+  pub(crate) f: f64,
+  pub(crate) g: Vec<f64>,
+  pub(crate) l: Vec<f64>,
+  pub(crate) u: Vec<f64>,
+  pub(crate) nbd: Vec<i64>,
+  pub(crate) ws: Workspace,
+  pub(crate) stop: StopHistory,
+}
+
+impl Checkpoint {
+  /// The current iterate, in original units.
+  pub fn x(&self) -> Cow<'_, [f64]> {
+    self.ws.original_x(&self.x)
+  }
+
+  /// f(x) at the current iterate, unscaled.
+  pub fn f(&self) -> f64 {
+    self.ws.original_f(self.f)
+  }
+
+  /// The number of iterations done, summed over restarts.
+  pub fn niter(&self) -> usize {
+    self.ws.niter()
+  }
+
+  /// Serialize to a versioned little-endian binary format.
+  pub fn to_bytes(&self) -> Vec<u8> {
+    let ws = &self.ws;
+    let mut w = Writer::default();
+    w.0.extend_from_slice(MAGIC);
+    w.u32(VERSION);
+
+    w.f64s(&self.x);
+    w.f64(self.f);
+    w.f64s(&self.g);
+    w.f64s(&self.l);
+    w.f64s(&self.u);
+    w.i64s(&self.nbd);
+
+    w.u64(ws.m as u64);
+    w.u64(ws.mmax as u64);
+    w.f64(ws.factr);
+    w.f64(ws.pgtol);
+    w.f64(ws.ftol);
+    w.f64(ws.gtol);
+    w.f64(ws.xtol);
+    w.u64(ws.maxls as u64);
+    match &ws.scale {
+      Some(scale) => {
+        w.u64(1);
+        w.f64s(scale);
+      }
+      None => w.u64(0),
+    }
+    w.f64(ws.fscale);
+    w.f64(ws.foffset);
+    w.i64(ws.task);
+    w.f64s(&ws.wa);
+    w.i64s(&ws.iwa);
+    w.i64s(&ws.csave);
+    w.f64s(&ws.dsave);
+    w.i64s(&ws.isave);
+    w.i64s(&ws.lsave);
+    w.u64(ws.nrestart as u64);
+    w.u64(ws.niter as u64);
+    w.u64(ws.nfg as u64);
+    w.u64(ws.iter_offset as u64);
+    let stop = &self.stop;
+    w.f64s(&stop.xold);
+    w.u64(stop.nsmall as u64);
+    w.f64s(&stop.f.iter().copied().collect::<Vec<_>>());
+    w.0
+  }
+
+  /// Deserialize a checkpoint written by `to_bytes`.
+  pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+    let mut r = Reader(bytes);
+    if r.take(MAGIC.len())? != MAGIC {
+      bail!("not an L-BFGS-B checkpoint");
+    }
+    let version = r.u32()?;
+    if version != VERSION {
+      bail!("unsupported checkpoint version {version}");
+    }
+
+    let x = r.f64s()?;
+    let f = r.f64()?;
+    let g = r.f64s()?;
+    let l = r.f64s()?;
+    let u = r.f64s()?;
+    let nbd = r.i64s()?;
+
+    let m = r.u64()? as usize;
+    let mmax = r.u64()? as usize;
+    // wa holds more than mmax values, so mmax is bounded by the input
+    // length before anything is allocated
+    match Workspace::wa_len(x.len(), mmax) {
+      Some(len) if mmax <= r.0.len() / 8 && len <= r.0.len() / 8 => (),
+      _ => bail!("inconsistent checkpoint"),
+    }
+    let param = LbfgsbParameter {
+      m: mmax,
+      factr: r.f64()?,
+      pgtol: r.f64()?,
+      ..Default::default()
+    };
+    let mut ws = Workspace::new(x.len(), &param);
+    ws.m = m;
+    ws.ftol = r.f64()?;
+    ws.gtol = r.f64()?;
+    ws.xtol = r.f64()?;
+    ws.maxls = r.u64()? as usize;
+    if r.u64()? != 0 {
+      ws.scale = Some(r.f64s()?);
+    }
+    ws.fscale = r.f64()?;
+    ws.foffset = r.f64()?;
+    ws.task = r.i64()?;
+    r.f64s_into(&mut ws.wa)?;
+    r.i64s_into(&mut ws.iwa)?;
+    r.i64s_into(&mut ws.csave)?;
+    r.f64s_into(&mut ws.dsave)?;
+    r.i64s_into(&mut ws.isave)?;
+    r.i64s_into(&mut ws.lsave)?;
+    ws.nrestart = r.u64()? as usize;
+    ws.niter = r.u64()? as usize;
+    ws.nfg = r.u64()? as usize;
+    ws.iter_offset = r.u64()? as usize;
+    let stop = StopHistory {
+      xold: r.f64s()?,
+      nsmall: r.u64()? as usize,
+      f: r.f64s()?.into(),
+    };
+
+    let n = x.len();
+    let nscale = ws.scale.as_ref().map_or(n, |s| s.len());
+    if ![0, n].contains(&stop.xold.len()) || [g.len(), l.len(), u.len(), nbd.len(), nscale].iter().any(|&k| k != n) || m > mmax || ws.fscale.is_nan() || ws.fscale <= 0.0 {
+      bail!("inconsistent checkpoint");
+    }
+    if !r.0.is_empty() {
+      bail!("trailing data in checkpoint");
+    }
+    // setulb indexes wa through isave without bounds checks
+    let counts = ws.isave[29] >= 0
+      && ws.isave[33] >= 0
+      && ws.iter_offset <= 1
+      && ws.niter.checked_add(ws.isave[29] as usize).is_some()
+      && ws.nfg.checked_add(ws.isave[33] as usize).is_some();
+    if !counts || !ws.is_consistent(n) {
+      bail!("inconsistent checkpoint");
+    }
+
+    Ok(Self { x, f, g, l, u, nbd, ws, stop })
+  }
+}
+
+#[derive(Default)]
+struct Writer(Vec<u8>);
+
+impl Writer {
+  fn u32(&mut self, v: u32) {
+    self.0.extend_from_slice(&v.to_le_bytes());
+  }
+
+  fn u64(&mut self, v: u64) {
+    self.0.extend_from_slice(&v.to_le_bytes());
+  }
+
+  fn i64(&mut self, v: i64) {
+    self.0.extend_from_slice(&v.to_le_bytes());
+  }
+
+  fn f64(&mut self, v: f64) {
+    self.0.extend_from_slice(&v.to_le_bytes());
+  }
+
+  fn f64s(&mut self, v: &[f64]) {
+    self.u64(v.len() as u64);
+    v.iter().for_each(|&vi| self.f64(vi));
+  }
+
+  fn i64s(&mut self, v: &[i64]) {
+    self.u64(v.len() as u64);
+    v.iter().for_each(|&vi| self.i64(vi));
+  }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+  fn take(&mut self, k: usize) -> Result<&[u8]> {
+    if self.0.len() < k {
+      bail!("truncated checkpoint");
+    }
+    let (head, tail) = self.0.split_at(k);
+    self.0 = tail;
+    Ok(head)
+  }
+
+  fn u32(&mut self) -> Result<u32> {
+    Ok(u32::from_le_bytes(self.take(4)?.try_into()?))
+  }
+
+  fn u64(&mut self) -> Result<u64> {
+    Ok(u64::from_le_bytes(self.take(8)?.try_into()?))
+  }
+
+  fn i64(&mut self) -> Result<i64> {
+    Ok(i64::from_le_bytes(self.take(8)?.try_into()?))
+  }
+
+  fn f64(&mut self) -> Result<f64> {
+    Ok(f64::from_le_bytes(self.take(8)?.try_into()?))
+  }
+
+  fn len(&mut self) -> Result<usize> {
+    let k = self.u64()? as usize;
+    // each element takes 8 bytes
+    if k > self.0.len() / 8 {
+      bail!("truncated checkpoint");
+    }
+    Ok(k)
+  }
+
+  fn f64s(&mut self) -> Result<Vec<f64>> {
+    let k = self.len()?;
+    (0..k).map(|_| self.f64()).collect()
+  }
+
+  fn i64s(&mut self) -> Result<Vec<i64>> {
+    let k = self.len()?;
+    (0..k).map(|_| self.i64()).collect()
+  }
+
+  fn f64s_into(&mut self, v: &mut [f64]) -> Result<()> {
+    if self.len()? != v.len() {
+      bail!("inconsistent checkpoint");
+    }
+    for vi in v.iter_mut() {
+      *vi = self.f64()?;
+    }
+    Ok(())
+  }
+
+  fn i64s_into(&mut self, v: &mut [i64]) -> Result<()> {
+    if self.len()? != v.len() {
+      bail!("inconsistent checkpoint");
+    }
+    for vi in v.iter_mut() {
+      *vi = self.i64()?;
+    }
+    Ok(())
+  }
+}
+// checkpoint:1 ends here
@@ -0,0 +1,93 @@
+// [[file:../lbfgsb.note::*hessian][hessian:1]]
+use anyhow::{ensure, Result};
+
+use crate::memory::CorrectionPairs;
+use crate::shared::dot;
+
+/// The inverse of the limited memory BFGS matrix of L-BFGS-B, as a linear
+/// operator in n variables.
+///
+/// L-BFGS-B keeps B = theta*I - W*M*W' in compact form. Its inverse H is the
+/// result of the BFGS inverse updates with the stored (s, y) pairs applied to
+/// H0 = I/theta, and is applied with the two-loop recursion in O(mn). If the
+/// run used variable scaling x = S y, the operator is S H S, the inverse
+/// Hessian with respect to x.
+#[derive(Debug, Clone)]
+pub struct InverseHessian {
+  n: usize,
+  pairs: CorrectionPairs,
+  // 1/(y's) of each pair
+  rho: Vec<f64>,
+}
+
+impl InverseHessian {
+  /// The operator for the correction pairs of a run, e.g.
+  /// `LbfgsbResult::memory`. Pairs without positive curvature y's > 0 are
+  /// skipped, and H0 = I is used if theta is not positive.
+  pub fn new(n: usize, pairs: &CorrectionPairs) -> Self {
+    let mut kept = CorrectionPairs {
+      theta: if pairs.theta.is_finite() && pairs.theta > 0.0 { pairs.theta } else { 1.0 },
+      scale: pairs.scale.clone().filter(|s| s.len() == n),
+      ..Default::default()
+    };
+    let mut rho = vec![];
+    for (s, y) in pairs.s.iter().zip(&pairs.y) {
+      let sy = dot(s, y);
+      if s.len() == n && y.len() == n && sy > 0.0 {
+        kept.s.push(s.clone());
+        kept.y.push(y.clone());
+        rho.push(1.0 / sy);
+      }
+    }
+    Self { n, pairs: kept, rho }
+  }
+
+  /// The number of variables.
+  pub fn n(&self) -> usize {
+    self.n
+  }
+
+  /// Compute H*v. Fails if v is not of length n.
+  pub fn apply(&self, v: &[f64]) -> Result<Vec<f64>> {
+    ensure!(v.len() == self.n, "vector of length {} for an operator in {} variables", v.len(), self.n);
+    Ok(self.product(v))
+  }
+
+  // H*v for v of length n
+  fn product(&self, v: &[f64]) -> Vec<f64> {
+    let pairs = &self.pairs;
+    let k = pairs.len();
+    let mut q = v.to_vec();
+    if let Some(scale) = &pairs.scale {
+      q.iter_mut().zip(scale).for_each(|(qj, sj)| *qj *= sj);
+    }
+    let mut alpha = vec![0.0; k];
+    for i in (0..k).rev() {
+      alpha[i] = self.rho[i] * dot(&pairs.s[i], &q);
+      q.iter_mut().zip(&pairs.y[i]).for_each(|(qj, yj)| *qj -= alpha[i] * yj);
+    }
+    q.iter_mut().for_each(|qj| *qj /= pairs.theta);
+    for (i, a) in alpha.iter().enumerate() {
+      let beta = self.rho[i] * dot(&pairs.y[i], &q);
+      q.iter_mut().zip(&pairs.s[i]).for_each(|(qj, sj)| *qj += (a - beta) * sj);
+    }
+    if let Some(scale) = &pairs.scale {
+      q.iter_mut().zip(scale).for_each(|(qj, sj)| *qj *= sj);
+    }
+    q
+  }
+
+  /// The dense n x n matrix H, as rows. Meant for small n.
+  pub fn to_dense(&self) -> Vec<Vec<f64>> {
+    let mut h = vec![vec![0.0; self.n]; self.n];
+    let mut e = vec![0.0; self.n];
+    for j in 0..self.n {
+      e[j] = 1.0;
+      // H is symmetric, so column j is row j
+      h[j] = self.product(&e);
+      e[j] = 0.0;
+    }
+    h
+  }
+}
+// hessian:1 ends here
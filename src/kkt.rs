@@ -0,0 +1,132 @@
+// [[file:../lbfgsb.note::*kkt][kkt:1]]
+/// Where a variable lies relative to its bounds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoundStatus {
+  /// Strictly between its bounds, or unbounded.
+  Free,
+  /// At its lower bound.
+  Lower,
+  /// At its upper bound.
+  Upper,
+  /// Lower and upper bounds are equal.
+  Fixed,
+}
+
+/// The first-order optimality conditions for one variable.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VariableKkt {
+  pub status: BoundStatus,
+  /// The component of the projected gradient, as in the pgtol test.
+  pub projected_gradient: f64,
+  /// The estimated Lagrange multiplier of the active bound: g_i at a lower
+  /// bound, -g_i at an upper bound, g_i for a fixed variable and 0 for a
+  /// free one. A negative value at a lower or upper bound means f can still
+  /// be decreased by leaving the bound.
+  pub multiplier: f64,
+}
+
+/// Post-solve check of the Karush-Kuhn-Tucker conditions of a bound
+/// constrained problem.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KktReport {
+  pub variables: Vec<VariableKkt>,
+  /// The largest violation of stationarity (the infinity norm of the
+  /// projected gradient), dual feasibility (negative multipliers) and
+  /// primal feasibility (bound violations). Zero at an exact solution.
+  pub residual: f64,
+}
+
+impl KktReport {
+  /// Check x with gradient g against bounds l, u of types nbd, with the
+  /// status of each variable as in `bound_status`.
+  pub fn new(x: &[f64], g: &[f64], l: &[f64], u: &[f64], nbd: &[i64]) -> Self {
+    let mut residual = 0.0f64;
+    let variables = (0..x.len())
+      .map(|i| {
+        let has_l = matches!(nbd[i], 1 | 2);
+        let has_u = matches!(nbd[i], 2 | 3);
+        let (xi, gi) = (x[i], g[i]);
+
+        // primal feasibility
+        if has_l {
+          residual = residual.max(l[i] - xi);
+        }
+        if has_u {
+          residual = residual.max(xi - u[i]);
+        }
+
+        let pg = projected_gradient(xi, gi, l[i], u[i], nbd[i]);
+        residual = residual.max(pg.abs());
+
+        let status = bound_status(xi, l[i], u[i], nbd[i]);
+        let multiplier = match status {
+          BoundStatus::Free => 0.0,
+          BoundStatus::Upper => -gi,
+          BoundStatus::Lower | BoundStatus::Fixed => gi,
+        };
+        if status == BoundStatus::Lower || status == BoundStatus::Upper {
+          // dual feasibility
+          residual = residual.max(-multiplier);
+        }
+
+        VariableKkt {
+          status,
+          projected_gradient: pg,
+          multiplier,
+        }
+      })
+      .collect();
+
+    Self { variables, residual }
+  }
+}
+
+// The projected gradient of one variable as computed by projgr.
+fn projected_gradient(x: f64, g: f64, l: f64, u: f64, nbd: i64) -> f64 {
+  if g < 0.0 && matches!(nbd, 2 | 3) {
+    g.max(x - u)
+  } else if g > 0.0 && matches!(nbd, 1 | 2) {
+    g.min(x - l)
+  } else {
+    g
+  }
+}
+
+/// The infinity norm of the projected gradient, as sbgnrm of setulb.
+pub(crate) fn projected_gradient_norm(x: &[f64], g: &[f64], l: &[f64], u: &[f64], nbd: &[i64]) -> f64 {
+  (0..x.len())
+    .map(|i| projected_gradient(x[i], g[i], l[i], u[i], nbd[i]).abs())
+    .fold(0.0, f64::max)
+}
+
+/// The status of x with bounds l, u of type nbd. Only a value exactly at a
+/// bound counts as active, as L-BFGS-B leaves it.
+pub fn bound_status(x: f64, l: f64, u: f64, nbd: i64) -> BoundStatus {
+  let has_l = matches!(nbd, 1 | 2);
+  let has_u = matches!(nbd, 2 | 3);
+  if has_l && has_u && l == u {
+    BoundStatus::Fixed
+  } else if has_l && x <= l {
+    BoundStatus::Lower
+  } else if has_u && x >= u {
+    BoundStatus::Upper
+  } else {
+    BoundStatus::Free
+  }
+}
+
+/// The indices of the variables at their lower and upper bounds. Fixed
+/// variables are in neither set.
+pub(crate) fn active_sets(x: &[f64], l: &[f64], u: &[f64], nbd: &[i64]) -> (Vec<usize>, Vec<usize>) {
+  let mut at_lower = vec![];
+  let mut at_upper = vec![];
+  for i in 0..x.len() {
+    match bound_status(x[i], l[i], u[i], nbd[i]) {
+      BoundStatus::Lower => at_lower.push(i),
+      BoundStatus::Upper => at_upper.push(i),
+      _ => {}
+    }
+  }
+  (at_lower, at_upper)
+}
+// kkt:1 ends here
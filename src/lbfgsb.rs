@@ -1,8 +1,8 @@
 // [[file:../lbfgsb.note::*imports][imports:1]]
 
-#[allow(clippy::all)]
+#[allow(clippy::all, dead_code)]
 mod bindings { include!(concat!(env!("OUT_DIR"), "/bindings_0.rs")); }
-use bindings::{integer, logical, NEW_X, START};
+use bindings::{integer, logical, ABNORMAL, FG_ST, NEW_X, START};
 
 extern "C" {
     #[allow(clashing_extern_declarations)]
@@ -26,68 +26,188 @@ extern "C" {
         isave: *mut integer,
         dsave: *mut f64,
     ) -> ::std::os::raw::c_int;
+
+    // the line search parameters, see build.rs
+    static mut setulb_ftol: f64;
+    static mut setulb_gtol: f64;
+    static mut setulb_xtol: f64;
+    static mut setulb_maxls: integer;
 }
 
-use anyhow::Result;
+use std::time::Instant;
+
+use anyhow::{ensure, Result};
 
-use crate::shared::{is_fg, LbfgsbParameter, LbfgsbProblem};
+use crate::best::BestTracker;
+use crate::cache::EvalCache;
+use crate::kkt::active_sets;
+use crate::memory::CorrectionPairs;
+use crate::nonfinite::NonFiniteGuard;
+use crate::objective::Objective;
+use crate::progress::Progress;
+use crate::scaling::{objective_factor, scale_factors, scaled_bounds, set_objective_scaling, to_original, to_scaled, ObjectiveScaling};
+use crate::shared::{is_fg, LbfgsbParameter, LbfgsbProblem, LbfgsbResult, StopReason};
+use crate::stopping::{StopChecker, StopHistory};
+use crate::timing::Timing;
+use crate::workspace::Workspace;
 // imports:1 ends here
 
 impl<'a, E> LbfgsbState<'a, E>
 where
-    E: FnMut(&[f64], &mut [f64]) -> Result<f64>,
+    E: Objective,
 {
-    pub(crate) fn minimize(&mut self) -> Result<()> {
+    pub(crate) fn minimize(&mut self) -> Result<LbfgsbResult> {
         let f = &mut self.problem.f;
         let x = &mut self.problem.x;
         let g = &mut self.problem.g;
-        let l = &self.problem.l;
-        let u = &self.problem.u;
         let nbd = &self.problem.nbd;
 
         let param = &self.param;
+        let ws = &mut self.ws;
+        // setulb sees the bounds of the scaled variables
+        let scaled = ws.scale.as_ref().map(|s| scaled_bounds(s, &self.problem.l, &self.problem.u));
+        let (l, u) = match &scaled {
+            Some((l, u)) => (l, u),
+            None => (&self.problem.l, &self.problem.u),
+        };
         let n = x.len();
-        let m = param.m;
-        loop {
+        let mut guard = NonFiniteGuard::new(param.non_finite);
+        let mut best = BestTracker::default();
+        let mut timing = Timing::default();
+        let mut criteria = StopChecker::new(param.stop, std::mem::take(&mut self.stop));
+        // a warm start begins at iteration 1; a resumed run is already past
+        // its first iteration
+        let mut seeded = ws.task != START as i64;
+        let mut started = seeded;
+        // a checkpoint is taken before the new iterate is checked
+        let mut stop = match ws.task == NEW_X as i64 {
+            true => criteria.check(&ws.original_x(x), ws.original_f(*f), ws.niter(), ws.nfg()),
+            false => None,
+        };
+        // the objective scaling of a new run may depend on f(x0)
+        let mut choose_fscale = !seeded && self.problem.objective_scaling == Some(ObjectiveScaling::FirstEvaluation);
+        unsafe {
+            // this instance is ours until we return
+            setulb_ftol = ws.ftol;
+            setulb_gtol = ws.gtol;
+            setulb_xtol = ws.xtol;
+            setulb_maxls = ws.maxls as integer;
+        }
+        while stop.is_none() {
+            let now = Instant::now();
             unsafe {
                 #[allow(clashing_extern_declarations)]
                 setulb(
-                    &(n as i64),             //x
-                    &(m as i64),             //x
-                    x.as_mut_ptr(),          //x
-                    l.as_ptr(),              //x
-                    u.as_ptr(),              //x
-                    nbd.as_ptr(),            //x
-                    f,                       //x
-                    g.as_mut_ptr(),          //x
-                    &param.factr,            //x
-                    &param.pgtol,            //x
-                    self.wa.as_mut_ptr(),    //x
-                    self.iwa.as_mut_ptr(),   //x
-                    &mut self.task,          //x
-                    &param.iprint,           //x
-                    self.csave.as_mut_ptr(), //x
-                    self.lsave.as_mut_ptr(), //x
-                    self.isave.as_mut_ptr(), //x
-                    self.dsave.as_mut_ptr(), //x
+                    &(n as i64),           //x
+                    &(ws.m as i64),        //x
+                    x.as_mut_ptr(),        //x
+                    l.as_ptr(),            //x
+                    u.as_ptr(),            //x
+                    nbd.as_ptr(),          //x
+                    f,                     //x
+                    g.as_mut_ptr(),        //x
+                    &ws.factr,             //x
+                    &ws.pgtol,             //x
+                    ws.wa.as_mut_ptr(),    //x
+                    ws.iwa.as_mut_ptr(),   //x
+                    &mut ws.task,          //x
+                    &param.iprint,         //x
+                    ws.csave.as_mut_ptr(), //x
+                    ws.lsave.as_mut_ptr(), //x
+                    ws.isave.as_mut_ptr(), //x
+                    ws.dsave.as_mut_ptr(), //x
                 );
             }
-            if is_fg(self.task) {
+            timing.solver += now.elapsed();
+            if is_fg(ws.task) {
                 // the minimization routine has returned to request the
                 // function f and gradient g values at the current x.
                 // Compute function value f for the sample problem.
-                *f = (self.problem.eval_fn)(x, g)?;
+                let now = Instant::now();
+                let xe = ws.original_x(x);
+                let fe = match &mut self.cache {
+                    Some(cache) => cache.evaluate(&mut self.problem.eval_fn, &xe, g)?,
+                    None => self.problem.eval_fn.evaluate(&xe, g)?,
+                };
+                timing.objective += now.elapsed();
+                if choose_fscale {
+                    choose_fscale = false;
+                    ws.fscale = objective_factor(fe);
+                }
+                ws.scale_gradient(g);
+                *f = ws.scale_objective(fe, g);
+                if ws.task == FG_ST as i64 && !seeded {
+                    // seed the limited memory matrix before the first
+                    // iteration, once the objective scaling is known
+                    seeded = true;
+                    if let Some(pairs) = &self.problem.warm_start {
+                        if pairs.seed(n, ws) {
+                            ws.iter_offset = 1;
+                        }
+                    }
+                }
+                if let Some(aux) = self.problem.eval_fn.aux() {
+                    self.problem.aux = Some(aux);
+                }
+                best.update(x, *f, g, l, u, nbd);
+                if !guard.check(&xe, f, g)? {
+                    stop = Some(StopReason::NonFinite);
+                    break;
+                }
+                if ws.task == FG_ST as i64 && !started {
+                    // the starting point of the run; the history of the
+                    // criteria carries over restarts
+                    started = true;
+                    criteria.start(&xe, ws.original_f(*f));
+                }
             // go back to the minimization routine.
-            } else if self.task == NEW_X as i64 {
+            } else if ws.task == NEW_X as i64 {
                 // the minimization routine has returned with a new iterate, and we have
                 // opted to continue the iteration.
+                if let Some(observer) = &mut self.problem.observer {
+                    if !observer(&Progress::new(x, *f, g, &self.problem.l, &self.problem.u, nbd, ws, criteria.history()))? {
+                        stop = Some(StopReason::Observer);
+                        break;
+                    }
+                }
+                if let Some(reason) = criteria.check(&ws.original_x(x), ws.original_f(*f), ws.niter(), ws.nfg()) {
+                    stop = Some(reason);
+                    break;
+                }
+            } else if ws.task == ABNORMAL as i64 && ws.nrestart < param.restart.max_restarts {
+                // the line search failed; setulb has restored the previous
+                // iterate, so start over from there with cleared memory.
+                timing.add_phases(&ws.dsave);
+                ws.restart(param.restart.m);
             } else {
                 // If task is neither FG nor NEW_X we terminate execution.
                 break;
             }
         }
 
-        Ok(())
+        let memory = CorrectionPairs::export(n, ws);
+        if param.return_best || stop == Some(StopReason::NonFinite) {
+            if let Some(best) = best.best() {
+                best.restore(x, f, g);
+            }
+        }
+        let (at_lower, at_upper) = active_sets(x, l, u, nbd);
+        timing.add_phases(&ws.dsave);
+
+        Ok(LbfgsbResult {
+            task: ws.task,
+            niter: ws.niter(),
+            nfg: ws.nfg(),
+            nrestart: ws.nrestart,
+            cache: self.cache.as_ref().map(|c| c.stats()),
+            stop,
+            non_finite: guard.count(),
+            best: best.into_best(),
+            memory,
+            at_lower,
+            at_upper,
+            timing,
+        })
     }
 }
 // 9e5b03b1 ends here
@@ -99,17 +219,20 @@ where
 /// # Parameters
 ///
 /// - bounds: a slice of tuple setting lower and upper bounds.
-/// - eval_fn: a closure evaluating f(x) and g(x). Returning Err value will cancel minimization.
+/// - eval_fn: an `Objective` evaluating f(x) and g(x), usually a closure. Returning Err value will cancel minimization.
 ///
 /// # Return
 ///
-/// - Returns final state containing x, f(x), g(x).
-pub fn lbfgsb<E>(problem: &mut LbfgsbProblem<E>, params: &LbfgsbParameter) -> Result<()>
+/// - Returns a summary of the run; the final x, f(x), g(x) are left in `problem`.
+pub fn lbfgsb<E>(problem: &mut LbfgsbProblem<E>, params: &LbfgsbParameter) -> Result<LbfgsbResult>
 where
-    E: FnMut(&[f64], &mut [f64]) -> Result<f64>,
+    E: Objective,
 {
-    let mut state = LbfgsbState::new(problem, params);
-    state.minimize()
+    let mut state = LbfgsbState::new(problem, params)?;
+    let mut result = state.minimize();
+    // report in original units, also after an error
+    to_original(&state.ws, state.problem, result.as_mut().ok());
+    result
     // Ok(state.x().to_vec())
 }
 // pub:1 ends here
@@ -120,126 +243,63 @@ where
 // [[file:../lbfgsb.note::9e5b03b1][9e5b03b1]]
 pub struct LbfgsbState<'a, E>
 where
-    E: FnMut(&[f64], &mut [f64]) -> Result<f64>,
+    E: Objective,
 {
     problem: &'a mut LbfgsbProblem<E>,
 
     param: &'a LbfgsbParameter,
 
-    /// wa is a double precision working array of length:
-    ///   (2mmax + 5)nmax + 12mmax^2 + 12mmax.
-    wa: Vec<f64>,
+    // The setulb working arrays, parameters and counters.
+    ws: Workspace,
 
-    // iwa is an integer working array of length 3nmax.
-    iwa: Vec<i64>,
+    // Remembers recently evaluated points, if enabled by `cache_size`.
+    cache: Option<EvalCache>,
 
-    // csave is a working string of characters of length 60.
-    // static char csave[60];
-    csave: [i64; 60],
-    // static double dsave[29];
-    //  dsave is a double precision working array of dimension 29.
-    // On exit with 'task' = NEW_X, the following information is
-    //                                                       available:
-    //   dsave(1) = current 'theta' in the BFGS matrix;
-    //   dsave(2) = f(x) in the previous iteration;
-    //   dsave(3) = factr*epsmch;
-    //   dsave(4) = 2-norm of the line search direction vector;
-    //   dsave(5) = the machine precision epsmch generated by the code;
-    //   dsave(7) = the accumulated time spent on searching for
-    //                                                   Cauchy points;
-    //   dsave(8) = the accumulated time spent on
-    //                                           subspace minimization;
-    //   dsave(9) = the accumulated time spent on line search;
-    //   dsave(11) = the slope of the line search function at
-    //                            the current point of line search;
-    //   dsave(12) = the maximum relative step length imposed in
-    //                                                     line search;
-    //   dsave(13) = the infinity norm of the projected gradient;
-    //   dsave(14) = the relative step length in the line search;
-    //   dsave(15) = the slope of the line search function at
-    //                           the starting point of the line search;
-    //   dsave(16) = the square of the 2-norm of the line search
-    //                                                direction vector.
-    dsave: [f64; 29],
-
-    // isave is an integer working array of dimension 44.
-    //   On exit with 'task' = NEW_X, the following information is
-    //                                                         available:
-    //     isave(22) = the total number of intervals explored in the
-    //                     search of Cauchy points;
-    //     isave(26) = the total number of skipped BFGS updates before
-    //                     the current iteration;
-    //     isave(30) = the number of current iteration;
-    //     isave(31) = the total number of BFGS updates prior the current
-    //                     iteration;
-    //     isave(33) = the number of intervals explored in the search of
-    //                     Cauchy point in the current iteration;
-    //     isave(34) = the total number of function and gradient
-    //                     evaluations;
-    //     isave(36) = the number of function value or gradient
-    //                              evaluations in the current iteration;
-    //     if isave(37) = 0  then the subspace argmin is within the box;
-    //     if isave(37) = 1  then the subspace argmin is beyond the box;
-    //     isave(38) = the number of free variables in the current
-    //                     iteration;
-    //     isave(39) = the number of active constraints in the current
-    //                     iteration;
-    //     n + 1 - isave(40) = the number of variables leaving the set of
-    //                       active constraints in the current iteration;
-    //     isave(41) = the number of variables entering the set of active
-    //                     constraints in the current iteration.
-    isave: [i64; 44],
-    // static logical lsave[4];
-    // lsave is a logical working array of dimension 4. On exit with 'task' =
-    // NEW_X, the following information is available:
-    //
-    //   If lsave(1) = .true. then the initial X has been replaced by its
-    //   projection in the feasible set;
-    //
-    //   If lsave(2) = .true.  then  the problem is constrained;
-    //
-    //   If lsave(3) = .true. then each variable has upper and lower bounds;
-    lsave: [i64; 4],
-
-    // Note in original fortran version:
-    //
-    // task is a working string of characters of length 60 indicating
-    // the current job when entering and leaving this subroutine.
-    //
-    // Note in L-BFGS-B-C
-    //
-    // Modified L-BFGS-B to use integers instead of strings, for testing the
-    // "task"
-    task: i64,
+    // The history of the stopping criteria, from a checkpoint.
+    stop: StopHistory,
 }
 
 
 impl<'a, E> LbfgsbState<'a, E>
 where
-    E: FnMut(&[f64], &mut [f64]) -> Result<f64>,
+    E: Objective,
 {
-  fn new(problem: &'a mut LbfgsbProblem<E>, param: &'a LbfgsbParameter) -> Self {
+  fn new(problem: &'a mut LbfgsbProblem<E>, param: &'a LbfgsbParameter) -> Result<Self> {
     let n = problem.x.len();
-    let m = param.m;
-    
-    // wa is a double precision working array of length
-    //   (2mmax + 5)nmax + 12mmax^2 + 12mmax.
-    let wa = vec![0.0; 2 * m * n + 5 * n + 11 * m * m + 8 * m];
-
-    // iwa is an integer working array of length 3nmax.
-    let iwa = vec![0; 3 * n];
+    ensure!(param.restart.m != Some(0), "the number of corrections after a restart must be positive");
+    let mut stop = StopHistory::default();
+    let ws = match problem.resume.take() {
+      Some(checkpoint) => {
+        ensure!(checkpoint.x.len() == n, "checkpoint has {} variables, not {n}", checkpoint.x.len());
+        problem.x = checkpoint.x;
+        problem.f = checkpoint.f;
+        problem.g = checkpoint.g;
+        problem.l = checkpoint.l;
+        problem.u = checkpoint.u;
+        problem.nbd = checkpoint.nbd;
+        stop = checkpoint.stop;
+        checkpoint.ws
+      }
+      None => {
+        let mut ws = Workspace::new(n, param);
+        // validate everything before the problem is rescaled: on an error
+        // the caller's problem is returned untouched
+        set_objective_scaling(problem, &mut ws)?;
+        if let Some(scale) = scale_factors(problem)? {
+          to_scaled(&scale, problem);
+          ws.scale = Some(scale);
+        }
+        ws
+      }
+    };
 
-    Self {
-      csave: [0; 60],
-      dsave: [0.0; 29],
-      isave: [0; 44],
-      lsave: [0; 4],
-      task: START.into(),
+    Ok(Self {
       problem,
       param,
-      wa,
-      iwa,
-    }
+      ws,
+      cache: (param.cache_size > 0).then(|| EvalCache::new(param.cache_size)),
+      stop,
+    })
   }
 
   // /// Final function value f(x)
@@ -0,0 +1,219 @@
+// [[file:../lbfgsb.note::*least_squares][least_squares:1]]
+use anyhow::{ensure, Result};
+
+use crate::objective::Objective;
+use crate::shared::{LbfgsbParameter, LbfgsbProblem, LbfgsbResult};
+
+/// Evaluates the m x n Jacobian of the residuals at x into a row-major
+/// slice. Like the residuals, it may borrow data for the lifetime 'a.
+pub type Jacobian<'a> = Box<dyn FnMut(&[f64], &mut [f64]) -> Result<()> + 'a>;
+
+/// The loss rho(z) applied to the squared scaled residuals z = (r_i / C)^2,
+/// as in `scipy.optimize.least_squares`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Loss {
+  /// rho(z) = z, ordinary least squares.
+  #[default]
+  Linear,
+  /// rho(z) = z for z <= 1, 2 sqrt(z) - 1 otherwise.
+  Huber,
+  /// rho(z) = 2 (sqrt(1 + z) - 1).
+  SoftL1,
+  /// rho(z) = ln(1 + z).
+  Cauchy,
+}
+
+impl Loss {
+  // rho(z) and rho'(z)
+  fn rho(self, z: f64) -> (f64, f64) {
+    match self {
+      Loss::Linear => (z, 1.0),
+      Loss::Huber if z <= 1.0 => (z, 1.0),
+      Loss::Huber => (2.0 * z.sqrt() - 1.0, 1.0 / z.sqrt()),
+      Loss::SoftL1 => {
+        let t = (1.0 + z).sqrt();
+        (2.0 * (t - 1.0), 1.0 / t)
+      }
+      Loss::Cauchy => ((1.0 + z).ln(), 1.0 / (1.0 + z)),
+    }
+  }
+}
+
+/// A bound constrained nonlinear least-squares problem: minimize the cost
+/// f(x) = 1/2 C^2 sum rho((r_i(x) / C)^2) of m residuals r(x), with
+/// gradient g = J' (rho' r). With the linear loss this is f = 1/2 |r|^2 and
+/// g = J'r.
+///
+/// `LeastSquares` is an `Objective`, so it can also be used as the
+/// `eval_fn` of an `LbfgsbProblem` directly.
+pub struct LeastSquares<'a, R> {
+  m: usize,
+  residuals: R,
+  jacobian: Option<Jacobian<'a>>,
+  loss: Loss,
+  f_scale: f64,
+  fd_step: f64,
+  // the (lower, upper) bounds of the variables, if known
+  bounds: Vec<(f64, f64)>,
+  r: Vec<f64>,
+  jac: Vec<f64>,
+}
+
+/// The solution of a least-squares problem.
+#[derive(Debug)]
+pub struct LeastSquaresResult {
+  pub x: Vec<f64>,
+  /// The cost f(x).
+  pub cost: f64,
+  /// The residuals r(x).
+  pub residuals: Vec<f64>,
+  /// The gradient of the cost.
+  pub gradient: Vec<f64>,
+  /// The summary of the L-BFGS-B run.
+  pub result: LbfgsbResult,
+}
+
+impl<'a, R> LeastSquares<'a, R>
+where
+  R: FnMut(&[f64], &mut [f64]) -> Result<()>,
+{
+  /// Fit `m` residuals evaluated by `residuals(x, r)`, with a forward
+  /// difference Jacobian unless one is set with `jacobian`.
+  pub fn new(m: usize, residuals: R) -> Self {
+    Self {
+      m,
+      residuals,
+      jacobian: None,
+      loss: Loss::Linear,
+      f_scale: 1.0,
+      fd_step: f64::EPSILON.sqrt(),
+      bounds: vec![],
+      r: vec![0.0; m],
+      jac: vec![],
+    }
+  }
+
+  /// Use an analytic Jacobian.
+  pub fn jacobian(mut self, jacobian: impl FnMut(&[f64], &mut [f64]) -> Result<()> + 'a) -> Self {
+    self.jacobian = Some(Box::new(jacobian));
+    self
+  }
+
+  /// Use a robust loss with soft margin C = `f_scale` between inlier and
+  /// outlier residuals.
+  pub fn loss(mut self, loss: Loss, f_scale: f64) -> Self {
+    self.loss = loss;
+    self.f_scale = f_scale;
+    self
+  }
+
+  /// The relative step h_j = fd_step max(|x_j|, 1) of the forward
+  /// difference Jacobian. Under `solve` the step is taken backward where
+  /// x_j + h_j would exceed the upper bound, as in scipy.
+  pub fn fd_step(mut self, fd_step: f64) -> Self {
+    self.fd_step = fd_step;
+    self
+  }
+
+  /// Evaluate the residuals at x.
+  pub fn residuals(&mut self, x: &[f64]) -> Result<Vec<f64>> {
+    let mut r = vec![0.0; self.m];
+    (self.residuals)(x, &mut r)?;
+    Ok(r)
+  }
+
+  // the Jacobian at x, with the residuals at x in self.r
+  fn evaluate_jacobian(&mut self, x: &[f64]) -> Result<()> {
+    let (m, n) = (self.m, x.len());
+    self.jac.resize(m * n, 0.0);
+    if let Some(jacobian) = &mut self.jacobian {
+      return jacobian(x, &mut self.jac);
+    }
+    let mut xh = x.to_vec();
+    let mut rh = vec![0.0; m];
+    for j in 0..n {
+      let mut h = self.fd_step * x[j].abs().max(1.0);
+      if let Some(&(l, u)) = self.bounds.get(j) {
+        if x[j] + h > u && x[j] - h >= l {
+          h = -h;
+        }
+      }
+      xh[j] = x[j] + h;
+      (self.residuals)(&xh, &mut rh)?;
+      // the step actually taken
+      let h = xh[j] - x[j];
+      xh[j] = x[j];
+      for (i, (rhi, ri)) in rh.iter().zip(&self.r).enumerate() {
+        self.jac[i * n + j] = (rhi - ri) / h;
+      }
+    }
+    Ok(())
+  }
+
+  // the cost and the weights rho'(z) of the residuals in self.r
+  fn cost(&self, weights: &mut [f64]) -> f64 {
+    let c2 = self.f_scale * self.f_scale;
+    let mut rho = 0.0;
+    for (w, ri) in weights.iter_mut().zip(&self.r) {
+      let (value, slope) = self.loss.rho(ri * ri / c2);
+      rho += value;
+      *w = slope;
+    }
+    0.5 * c2 * rho
+  }
+
+  /// Minimize the cost from x0 within `bounds`, one (lower, upper) pair per
+  /// variable. Fails if the number of bounds differs from the length of x0.
+  pub fn solve<B>(mut self, x0: Vec<f64>, bounds: B, param: &LbfgsbParameter) -> Result<LeastSquaresResult>
+  where
+    B: IntoIterator<Item = (Option<f64>, Option<f64>)>,
+  {
+    ensure!(self.f_scale > 0.0 && self.f_scale.is_finite(), "invalid f_scale {}", self.f_scale);
+    let bounds: Vec<_> = bounds.into_iter().collect();
+    ensure!(bounds.len() == x0.len(), "{} bounds for {} variables", bounds.len(), x0.len());
+    let bound = |b: Option<f64>, inf: f64| b.unwrap_or(inf);
+    self.bounds = bounds.iter().map(|&(l, u)| (bound(l, f64::NEG_INFINITY), bound(u, f64::INFINITY))).collect();
+    let mut problem = LbfgsbProblem::build(x0, |x: &[f64], g: &mut [f64]| self.evaluate(x, g));
+    problem.set_bounds(bounds);
+    let result = crate::router::lbfgsb(&mut problem, param)?;
+    let (x, f, g) = (std::mem::take(&mut problem.x), problem.f, std::mem::take(&mut problem.g));
+    drop(problem);
+    let residuals = self.residuals(&x)?;
+    Ok(LeastSquaresResult {
+      x,
+      cost: f,
+      residuals,
+      gradient: g,
+      result,
+    })
+  }
+}
+
+impl<R> Objective for LeastSquares<'_, R>
+where
+  R: FnMut(&[f64], &mut [f64]) -> Result<()>,
+{
+  type Aux = ();
+
+  fn evaluate(&mut self, x: &[f64], g: &mut [f64]) -> Result<f64> {
+    let (m, n) = (self.m, x.len());
+    (self.residuals)(x, &mut self.r)?;
+    self.evaluate_jacobian(x)?;
+    let mut weights = vec![0.0; m];
+    let f = self.cost(&mut weights);
+    // g = J' (rho' r)
+    g.fill(0.0);
+    for (i, (w, ri)) in weights.iter().zip(&self.r).enumerate() {
+      let row = &self.jac[i * n..][..n];
+      g.iter_mut().zip(row).for_each(|(gj, jij)| *gj += jij * w * ri);
+    }
+    Ok(f)
+  }
+
+  fn value_only(&mut self, x: &[f64]) -> Result<f64> {
+    (self.residuals)(x, &mut self.r)?;
+    let mut weights = vec![0.0; self.m];
+    Ok(self.cost(&mut weights))
+  }
+}
+// least_squares:1 ends here
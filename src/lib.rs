@@ -1,8 +1,32 @@
 #![allow(nonstandard_style)]
 
 // We don't need this, this is just for testing compiling to catch errors earlier
+#[allow(dead_code)]
 mod lbfgsb;
 
+pub mod best;
+pub mod block;
+pub mod builder;
+pub mod cache;
+pub mod checkpoint;
+pub mod hessian;
+pub mod kkt;
+pub mod least_squares;
+pub mod memory;
+pub mod named;
+pub mod nnls;
+pub mod nonfinite;
+pub mod objective;
+pub mod operator;
+pub mod progress;
+pub mod qp;
 pub mod router;
+pub mod scaling;
 pub mod shared;
+pub mod stopping;
+pub mod subset;
+pub mod timing;
+pub mod transform;
+pub mod uncertainty;
+mod workspace;
 
@@ -0,0 +1,322 @@
+// [[file:../lbfgsb.note::*memory][memory:1]]
+use crate::hessian::InverseHessian;
+use crate::shared::dot;
+use crate::workspace::Workspace;
+
+/// The limited memory BFGS matrix of a run, as stored (s, y) correction
+/// pairs ordered from oldest to newest, together with the scaling `theta`.
+///
+/// A finished run exports its pairs in `LbfgsbResult::memory`. Setting them
+/// as `LbfgsbProblem::warm_start` of a closely related problem starts the
+/// next run with this curvature information instead of theta = 1 and an
+/// empty memory.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CorrectionPairs {
+  /// The steps s_k = x_{k+1} - x_k.
+  pub s: Vec<Vec<f64>>,
+  /// The gradient changes y_k = g_{k+1} - g_k, of the unscaled objective.
+  pub y: Vec<Vec<f64>>,
+  /// The scaling theta = y'y / y's of the latest update.
+  pub theta: f64,
+  /// The variable scaling of the run, if any: s and y are then steps and
+  /// gradient changes in the scaled variables x_i / scale_i.
+  pub scale: Option<Vec<f64>>,
+}
+
+// Factor the leading col x col block of the symmetric matrix `a` (leading
+// dimension `lda`, upper triangle) as R'R like LINPACK dpofa, with R stored
+// in the upper triangle. Returns false if `a` is not positive definite.
+fn dpofa(a: &mut [f64], lda: usize, col: usize) -> bool {
+  for j in 0..col {
+    let mut s = 0.0;
+    for k in 0..j {
+      let mut t = a[k + j * lda];
+      for i in 0..k {
+        t -= a[i + k * lda] * a[i + j * lda];
+      }
+      t /= a[k + k * lda];
+      a[k + j * lda] = t;
+      s += t * t;
+    }
+    s = a[j + j * lda] - s;
+    if s <= 0.0 {
+      return false;
+    }
+    a[j + j * lda] = s.sqrt();
+  }
+  true
+}
+
+// Offsets of the arrays setulb keeps in `wa`, as set up at START in
+// isave(4)..isave(10).
+struct Layout {
+  ws: usize,
+  wy: usize,
+  sy: usize,
+  ss: usize,
+  wt: usize,
+  snd: usize,
+}
+
+impl Layout {
+  fn new(isave: &[i64]) -> Option<Self> {
+    if isave[3] < 1 {
+      return None;
+    }
+    let at = |i: usize| isave[i] as usize - 1;
+    Some(Self {
+      ws: at(3),
+      wy: at(4),
+      sy: at(5),
+      ss: at(6),
+      wt: at(7),
+      snd: at(9),
+    })
+  }
+}
+
+impl CorrectionPairs {
+  /// The number of stored pairs.
+  pub fn len(&self) -> usize {
+    self.s.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.s.is_empty()
+  }
+
+  /// The inverse of the limited memory BFGS matrix in `n` variables, an
+  /// approximation of the inverse Hessian.
+  pub fn inverse_hessian(&self, n: usize) -> InverseHessian {
+    InverseHessian::new(n, self)
+  }
+
+  /// Read the pairs stored in the setulb workspace.
+  pub(crate) fn export(n: usize, ws: &Workspace) -> Self {
+    let (m, wa, isave, dsave) = (ws.m, &ws.wa, &ws.isave, &ws.dsave);
+    let layout = match Layout::new(isave) {
+      Some(layout) => layout,
+      None => return Self::default(),
+    };
+    // head and col of mainlb, in isave(27) and isave(28)
+    let head = isave[26].max(1) as usize;
+    let col = isave[27].clamp(0, m as i64) as usize;
+
+    let mut pairs = Self {
+      theta: dsave[0],
+      scale: ws.scale.clone(),
+      ..Self::default()
+    };
+    for j in 0..col {
+      let p = (head - 1 + j) % m;
+      pairs.s.push(wa[layout.ws + p * n..][..n].to_vec());
+      pairs.y.push(wa[layout.wy + p * n..][..n].to_vec());
+    }
+    if ws.fscale != 1.0 {
+      pairs = pairs.with_objective_factor(1.0 / ws.fscale);
+    }
+    pairs
+  }
+
+  /// Load the newest `m` usable pairs into the setulb workspace. Must be
+  /// called when setulb first returns with FG_ST. Returns false if nothing
+  /// was loaded. Pairs from a run with different variable scaling are
+  /// converted to the scaling of the workspace, and so is y for a scaled
+  /// objective.
+  ///
+  /// Besides S, Y and the matrices formed by matupd and formt, formk keeps
+  /// the blocks of its middle matrix for the free variables of the previous
+  /// iteration and only updates them for variables entering or leaving the
+  /// free set. We form these blocks for all variables free and mark every
+  /// variable as previously free. The iteration counter is set to 1 so that
+  /// freev counts the variables that are no longer free; unconstrained
+  /// problems skip freev, so its counts are initialized here as well.
+  //
+  // This writes the private state of mainlb in the C sources under lib/,
+  // with 0-based indices into the save arrays:
+  //
+  // - isave[3..8], isave[9]: the offsets of ws, wy, sy, ss, wt and snd in
+  //   wa (read only)
+  // - isave[26], isave[27], isave[28]: head, col and itail
+  // - isave[29], isave[30]: iter and iupdat
+  // - isave[37], isave[39], isave[40]: nfree, ileave and nenter
+  // - dsave[0]: theta
+  // - lsave[3]: updatd
+  // - iwa[..n]: index, the free variables of the previous iteration
+  //
+  // Nothing is written unless the workspace passes `is_consistent`.
+  pub(crate) fn seed(&self, n: usize, ws: &mut Workspace) -> bool {
+    if !ws.is_consistent(n) {
+      return false;
+    }
+    // pairs in another number of variables are skipped before converting,
+    // and all of them if their scaling is for another number of variables
+    if self.scale.as_ref().is_some_and(|s| s.len() != n) {
+      return false;
+    }
+    let sized;
+    let this = if self.s.len() != self.y.len() || self.s.iter().chain(&self.y).any(|v| v.len() != n) {
+      let (s, y) = self.s.iter().zip(&self.y).filter(|(s, y)| s.len() == n && y.len() == n).map(|(s, y)| (s.clone(), y.clone())).unzip();
+      sized = Self {
+        s,
+        y,
+        theta: self.theta,
+        scale: self.scale.clone(),
+      };
+      &sized
+    } else {
+      self
+    };
+    let converted;
+    let this = if this.scale != ws.scale || ws.fscale != 1.0 {
+      converted = this.rescaled(ws.scale.as_deref()).with_objective_factor(ws.fscale);
+      &converted
+    } else {
+      this
+    };
+
+    let m = ws.m;
+    let Workspace { wa, iwa, isave, dsave, lsave, .. } = ws;
+    let layout = match Layout::new(isave) {
+      Some(layout) => layout,
+      None => return false,
+    };
+    let theta = this.theta;
+    if !(theta.is_finite() && theta > 0.0) {
+      return false;
+    }
+    let usable: Vec<_> = this
+      .s
+      .iter()
+      .zip(&this.y)
+      .filter(|(s, y)| {
+        s.len() == n
+          && y.len() == n
+          && s.iter().chain(y.iter()).all(|v| v.is_finite())
+          && dot(s, y) > 0.0
+      })
+      .collect();
+    let pairs = &usable[usable.len().saturating_sub(m)..];
+    let col = pairs.len();
+    if col == 0 {
+      return false;
+    }
+
+    // SY (lower triangle) and SS (upper triangle) as formed by matupd
+    let mut sy = vec![0.0; m * m];
+    let mut ss = vec![0.0; m * m];
+    for i in 0..col {
+      for j in 0..=i {
+        sy[i + j * m] = dot(pairs[i].0, pairs[j].1);
+        ss[j + i * m] = dot(pairs[j].0, pairs[i].0);
+      }
+    }
+
+    // T = theta*SS + L*D^(-1)*L' and its Cholesky factor, as in formt
+    let mut wt = vec![0.0; m * m];
+    for i in 0..col {
+      for j in i..col {
+        let mut t = theta * ss[i + j * m];
+        for k in 0..i {
+          t += sy[i + k * m] * sy[j + k * m] / sy[k + k * m];
+        }
+        wt[i + j * m] = t;
+      }
+    }
+    if !dpofa(&mut wt, m, col) {
+      return false;
+    }
+
+    // the blocks Y'ZZ'Y, S'AA'S and S'ZZ'Y / S'AA'Y of formk with all
+    // variables free (Z = I, A empty)
+    let m2 = 2 * m;
+    let mut wn1 = vec![0.0; m2 * m2];
+    for i in 0..col {
+      for j in 0..=i {
+        wn1[i + j * m2] = dot(pairs[i].1, pairs[j].1);
+      }
+      for j in i..col {
+        wn1[(m + i) + j * m2] = dot(pairs[i].0, pairs[j].1);
+      }
+    }
+
+    for (j, (s, y)) in pairs.iter().enumerate() {
+      wa[layout.ws + j * n..][..n].copy_from_slice(s);
+      wa[layout.wy + j * n..][..n].copy_from_slice(y);
+    }
+    wa[layout.sy..][..m * m].copy_from_slice(&sy);
+    wa[layout.ss..][..m * m].copy_from_slice(&ss);
+    wa[layout.wt..][..m * m].copy_from_slice(&wt);
+    wa[layout.snd..][..m2 * m2].copy_from_slice(&wn1);
+
+    for (i, v) in iwa[..n].iter_mut().enumerate() {
+      *v = i as i64 + 1;
+    }
+    isave[26] = 1;
+    isave[27] = col as i64;
+    isave[28] = col as i64;
+    isave[29] = 1;
+    isave[30] = col as i64;
+    isave[37] = n as i64;
+    isave[39] = n as i64 + 1;
+    isave[40] = 0;
+    dsave[0] = theta;
+    lsave[3] = 1;
+
+    true
+  }
+
+  // The pairs in variables scaled by `scale` instead of `self.scale`. s'y
+  // is unchanged.
+  fn rescaled(&self, scale: Option<&[f64]>) -> Self {
+    let n = self.s.first().map_or(0, |s| s.len());
+    let factor = |i: usize| {
+      let old = self.scale.as_ref().map_or(1.0, |s| s[i]);
+      let new = scale.map_or(1.0, |s| s[i]);
+      old / new
+    };
+    let r: Vec<f64> = (0..n).map(factor).collect();
+    Self {
+      s: self.s.iter().map(|s| s.iter().zip(&r).map(|(si, ri)| si * ri).collect()).collect(),
+      y: self.y.iter().map(|y| y.iter().zip(&r).map(|(yi, ri)| yi / ri).collect()).collect(),
+      theta: self.theta,
+      scale: scale.map(|s| s.to_vec()),
+    }
+  }
+
+  // The pairs in the variables `index` only.
+  pub(crate) fn gather(&self, index: &[usize]) -> Self {
+    let pick = |v: &Vec<f64>| index.iter().map(|&i| v[i]).collect();
+    Self {
+      s: self.s.iter().map(pick).collect(),
+      y: self.y.iter().map(pick).collect(),
+      theta: self.theta,
+      scale: self.scale.as_ref().map(pick),
+    }
+  }
+
+  // The pairs of variables `index` in `n` variables, with zero steps and
+  // gradient changes for the others.
+  pub(crate) fn scatter(&self, index: &[usize], n: usize) -> Self {
+    let place = |v: &Vec<f64>, fill: f64| {
+      let mut full = vec![fill; n];
+      index.iter().zip(v).for_each(|(&i, vi)| full[i] = *vi);
+      full
+    };
+    Self {
+      s: self.s.iter().map(|s| place(s, 0.0)).collect(),
+      y: self.y.iter().map(|y| place(y, 0.0)).collect(),
+      theta: self.theta,
+      scale: self.scale.as_ref().map(|s| place(s, 1.0)),
+    }
+  }
+
+  // The pairs for the objective multiplied by `c`: y and theta scale with
+  // the gradient.
+  fn with_objective_factor(mut self, c: f64) -> Self {
+    self.y.iter_mut().flatten().for_each(|yi| *yi *= c);
+    self.theta *= c;
+    self
+  }
+}
+// memory:1 ends here
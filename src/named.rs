@@ -0,0 +1,121 @@
+// [[file:../lbfgsb.note::*named][named:1]]
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{bail, ensure, Result};
+
+use crate::kkt::{bound_status, BoundStatus};
+use crate::objective::Objective;
+use crate::shared::{LbfgsbParameter, LbfgsbProblem, LbfgsbResult};
+
+/// A parameter of a model, with its initial value and bounds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameter {
+  pub name: String,
+  pub value: f64,
+  pub lower: Option<f64>,
+  pub upper: Option<f64>,
+}
+
+/// Parameters declared by name, laid out in a flat vector in the order of
+/// declaration. The objective sees the flat vector; use `index` to find a
+/// parameter in it.
+#[derive(Debug, Clone, Default)]
+pub struct ParameterSet {
+  params: Vec<Parameter>,
+  index: HashMap<String, usize>,
+}
+
+/// The solution of a minimization over a `ParameterSet`, by name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Solution {
+  /// The final value of each parameter.
+  pub values: BTreeMap<String, f64>,
+  /// Where each parameter ended relative to its bounds.
+  pub status: BTreeMap<String, BoundStatus>,
+  /// f at the final values.
+  pub f: f64,
+}
+
+impl Solution {
+  /// The final value of parameter `name`.
+  pub fn value(&self, name: &str) -> Option<f64> {
+    self.values.get(name).copied()
+  }
+}
+
+impl ParameterSet {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Declare a parameter and return its position in the flat vector.
+  pub fn add(&mut self, name: &str, value: f64, lower: Option<f64>, upper: Option<f64>) -> Result<usize> {
+    if self.index.contains_key(name) {
+      bail!("parameter {name} declared twice");
+    }
+    if let (Some(l), Some(u)) = (lower, upper) {
+      ensure!(l <= u, "parameter {name} has lower bound {l} > upper bound {u}");
+    }
+    let i = self.params.len();
+    self.params.push(Parameter {
+      name: name.into(),
+      value,
+      lower,
+      upper,
+    });
+    self.index.insert(name.into(), i);
+    Ok(i)
+  }
+
+  /// The number of parameters.
+  pub fn len(&self) -> usize {
+    self.params.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.params.is_empty()
+  }
+
+  /// The position of parameter `name` in the flat vector.
+  pub fn index(&self, name: &str) -> Option<usize> {
+    self.index.get(name).copied()
+  }
+
+  /// The parameters in the order of the flat vector.
+  pub fn parameters(&self) -> &[Parameter] {
+    &self.params
+  }
+
+  /// A problem over the flat vector of initial values and bounds.
+  pub fn problem<E: Objective>(&self, eval_fn: E) -> LbfgsbProblem<E> {
+    let mut problem = LbfgsbProblem::build(self.params.iter().map(|p| p.value).collect(), eval_fn);
+    problem.set_bounds(self.params.iter().map(|p| (p.lower, p.upper)));
+    problem
+  }
+
+  /// The solution by name from the final x and f of `problem`. Fails if
+  /// `problem` is not over as many variables as there are parameters.
+  pub fn solution<E: Objective>(&self, problem: &LbfgsbProblem<E>) -> Result<Solution> {
+    let n = problem.x.len();
+    ensure!(n == self.len(), "problem has {n} variables, not {} parameters", self.len());
+    let mut values = BTreeMap::new();
+    let mut status = BTreeMap::new();
+    for (i, p) in self.params.iter().enumerate() {
+      values.insert(p.name.clone(), problem.x[i]);
+      status.insert(p.name.clone(), bound_status(problem.x[i], problem.l[i], problem.u[i], problem.nbd[i]));
+    }
+    Ok(Solution {
+      values,
+      status,
+      f: problem.f,
+    })
+  }
+
+  /// Minimize `eval_fn` over the parameters.
+  pub fn minimize<E: Objective>(&self, eval_fn: E, param: &LbfgsbParameter) -> Result<(Solution, LbfgsbResult)> {
+    let mut problem = self.problem(eval_fn);
+    let result = crate::router::lbfgsb(&mut problem, param)?;
+    Ok((self.solution(&problem)?, result))
+  }
+}
+// named:1 ends here
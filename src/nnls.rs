@@ -0,0 +1,44 @@
+// [[file:../lbfgsb.note::*nnls][nnls:1]]
+use anyhow::{ensure, Result};
+
+use crate::operator::LinearOperator;
+use crate::shared::{LbfgsbParameter, LbfgsbProblem, LbfgsbResult};
+
+/// The solution of a non-negative least-squares problem.
+#[derive(Debug)]
+pub struct NnlsResult {
+  pub x: Vec<f64>,
+  /// The residual norm |Ax - b|.
+  pub rnorm: f64,
+  /// The summary of the L-BFGS-B run.
+  pub result: LbfgsbResult,
+}
+
+/// Solve min |Ax - b| subject to x >= 0 from x = 0.
+///
+/// setulb minimizes f = 1/2 |Ax - b|^2 with g = A'(Ax - b), so that each
+/// evaluation takes one product with A and one with A'. Use a small pgtol
+/// for an accurate solution, as the problem is often poorly scaled.
+///
+/// Only `b` is checked against the size of A. The operator must itself
+/// write vectors of its declared `nrows` and `ncols`, see `FnOperator`.
+pub fn nnls<A: LinearOperator>(a: &A, b: &[f64], param: &LbfgsbParameter) -> Result<NnlsResult> {
+  let (m, n) = (a.nrows(), a.ncols());
+  ensure!(b.len() == m, "b has length {}, A has {m} rows", b.len());
+
+  let mut r = vec![0.0; m];
+  let evaluate = |x: &[f64], g: &mut [f64]| {
+    a.apply(x, &mut r);
+    r.iter_mut().zip(b).for_each(|(ri, bi)| *ri -= bi);
+    a.apply_transpose(&r, g);
+    Ok(0.5 * r.iter().map(|ri| ri * ri).sum::<f64>())
+  };
+  let mut problem = LbfgsbProblem::build(vec![0.0; n], evaluate);
+  problem.set_bounds(vec![(Some(0.0), None); n]);
+  let result = crate::router::lbfgsb(&mut problem, param)?;
+
+  let x = std::mem::take(&mut problem.x);
+  let rnorm = (2.0 * problem.f).sqrt();
+  Ok(NnlsResult { x, rnorm, result })
+}
+// nnls:1 ends here
@@ -0,0 +1,93 @@
+// [[file:../lbfgsb.note::*nonfinite][nonfinite:1]]
+use std::fmt;
+
+use anyhow::Result;
+
+/// What to do when `eval_fn` returns a NaN or infinite f(x) or g(x).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NonFinitePolicy {
+  /// Pass the values on to setulb unchanged.
+  #[default]
+  Ignore,
+  /// Treat the point as a step too long: report a huge f(x) together with
+  /// the last finite gradient, so that the line search backtracks.
+  Backtrack,
+  /// Abort minimization with a `NonFiniteError`.
+  Abort,
+  /// Stop minimization and leave the best finite point evaluated so far in
+  /// the problem.
+  ReturnBest,
+}
+
+/// Error returned when `eval_fn` produced a non-finite value at `x`.
+#[derive(Debug, Clone)]
+pub struct NonFiniteError {
+  /// The point at which f(x) or g(x) was not finite.
+  pub x: Vec<f64>,
+  /// The function value returned at `x`.
+  pub f: f64,
+}
+
+impl fmt::Display for NonFiniteError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "non-finite objective (f = {}) at x = {:?}", self.f, self.x)
+  }
+}
+
+impl std::error::Error for NonFiniteError {}
+
+/// Applies a `NonFinitePolicy` to each evaluation.
+pub(crate) struct NonFiniteGuard {
+  policy: NonFinitePolicy,
+  // the last finite f and g
+  last: Option<(f64, Vec<f64>)>,
+  count: usize,
+}
+
+impl NonFiniteGuard {
+  pub fn new(policy: NonFinitePolicy) -> Self {
+    Self {
+      policy,
+      last: None,
+      count: 0,
+    }
+  }
+
+  /// The number of non-finite evaluations seen.
+  pub fn count(&self) -> usize {
+    self.count
+  }
+
+  /// Check f and g just evaluated at x, replacing them as required by the
+  /// policy. Returns false if minimization should stop and the best point
+  /// seen be restored.
+  pub fn check(&mut self, x: &[f64], f: &mut f64, g: &mut [f64]) -> Result<bool> {
+    if self.policy == NonFinitePolicy::Ignore {
+      return Ok(true);
+    }
+
+    if f.is_finite() && g.iter().all(|gi| gi.is_finite()) {
+      match &mut self.last {
+        Some((flast, glast)) => {
+          *flast = *f;
+          glast.copy_from_slice(g);
+        }
+        None => self.last = Some((*f, g.to_vec())),
+      }
+      return Ok(true);
+    }
+
+    self.count += 1;
+    match (self.policy, &self.last) {
+      (NonFinitePolicy::Backtrack, Some((flast, glast))) => {
+        *f = flast + 1E10 * flast.abs().max(1.0);
+        g.copy_from_slice(glast);
+        Ok(true)
+      }
+      (NonFinitePolicy::ReturnBest, Some(_)) => Ok(false),
+      // nothing finite to fall back to
+      _ => Err(NonFiniteError { x: x.to_vec(), f: *f }.into()),
+    }
+  }
+}
+// nonfinite:1 ends here
@@ -0,0 +1,80 @@
+// [[file:../lbfgsb.note::*objective][objective:1]]
+use anyhow::Result;
+
+/// The function to be minimized by L-BFGS-B.
+///
+/// Any closure evaluating f(x) and g(x) as `FnMut(&[f64], &mut [f64]) ->
+/// Result<f64>` is an `Objective`, so simple problems can keep passing
+/// closures while complex models can be written as structs.
+pub trait Objective {
+  /// Auxiliary data reported alongside each evaluation, e.g. model
+  /// diagnostics. Use `()` if there is nothing to report.
+  type Aux;
+
+  /// Evaluate f(x) and g(x) at `x`, writing the gradient into `g`.
+  /// Returning Err value will cancel minimization.
+  fn evaluate(&mut self, x: &[f64], g: &mut [f64]) -> Result<f64>;
+
+  /// Evaluate f(x) only.
+  ///
+  /// The default implementation discards the gradient of `evaluate`.
+  /// Override it if the function value is much cheaper on its own.
+  fn value_only(&mut self, x: &[f64]) -> Result<f64> {
+    let mut g = vec![0.0; x.len()];
+    self.evaluate(x, &mut g)
+  }
+
+  /// Evaluate g(x) only, writing the gradient into `g`.
+  ///
+  /// The default implementation discards the function value of `evaluate`.
+  fn gradient_only(&mut self, x: &[f64], g: &mut [f64]) -> Result<()> {
+    self.evaluate(x, g)?;
+    Ok(())
+  }
+
+  /// Auxiliary data of the most recent evaluation, if any.
+  fn aux(&mut self) -> Option<Self::Aux> {
+    None
+  }
+}
+
+impl<F> Objective for F
+where
+  F: FnMut(&[f64], &mut [f64]) -> Result<f64>,
+{
+  type Aux = ();
+
+  fn evaluate(&mut self, x: &[f64], g: &mut [f64]) -> Result<f64> {
+    self(x, g)
+  }
+}
+// objective:1 ends here
+
+// [[file:../lbfgsb.note::*check][check:1]]
+/// Compare the analytic gradient of `objective` at `x` against central
+/// finite differences with step `h`, using `value_only` for the function
+/// values.
+///
+/// # Return
+///
+/// - Returns the largest absolute difference over all components.
+pub fn check_gradient<O: Objective>(objective: &mut O, x: &[f64], h: f64) -> Result<f64> {
+  let n = x.len();
+  let mut g = vec![0.0; n];
+  objective.gradient_only(x, &mut g)?;
+
+  let mut xh = x.to_vec();
+  let mut max_err: f64 = 0.0;
+  for i in 0..n {
+    xh[i] = x[i] + h;
+    let fp = objective.value_only(&xh)?;
+    xh[i] = x[i] - h;
+    let fm = objective.value_only(&xh)?;
+    xh[i] = x[i];
+    let gi = (fp - fm) / (2.0 * h);
+    max_err = max_err.max((gi - g[i]).abs());
+  }
+
+  Ok(max_err)
+}
+// check:1 ends here
@@ -0,0 +1,122 @@
+// [[file:../lbfgsb.note::*operator][operator:1]]
+use anyhow::{ensure, Result};
+
+/// A linear map A from R^ncols to R^nrows, given by its products with
+/// vectors.
+pub trait LinearOperator {
+  fn nrows(&self) -> usize;
+
+  fn ncols(&self) -> usize;
+
+  /// y = A x.
+  fn apply(&self, x: &[f64], y: &mut [f64]);
+
+  /// x = A' y.
+  fn apply_transpose(&self, y: &[f64], x: &mut [f64]);
+}
+
+/// A dense matrix stored row by row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DenseMatrix {
+  nrows: usize,
+  ncols: usize,
+  data: Vec<f64>,
+}
+
+impl DenseMatrix {
+  /// The nrows x ncols matrix with entries `data` in row-major order.
+  /// Fails if data is not of length nrows * ncols.
+  pub fn new(nrows: usize, ncols: usize, data: Vec<f64>) -> Result<Self> {
+    ensure!(
+      nrows.checked_mul(ncols) == Some(data.len()),
+      "matrix data of length {} for {nrows} x {ncols} entries",
+      data.len()
+    );
+    Ok(Self { nrows, ncols, data })
+  }
+
+  /// The matrix with the given rows. Fails if the rows differ in length.
+  pub fn from_rows(rows: &[Vec<f64>]) -> Result<Self> {
+    let ncols = rows.first().map_or(0, |r| r.len());
+    ensure!(rows.iter().all(|r| r.len() == ncols), "rows of different length");
+    Self::new(rows.len(), ncols, rows.concat())
+  }
+
+  fn row(&self, i: usize) -> &[f64] {
+    &self.data[i * self.ncols..][..self.ncols]
+  }
+}
+
+impl LinearOperator for DenseMatrix {
+  fn nrows(&self) -> usize {
+    self.nrows
+  }
+
+  fn ncols(&self) -> usize {
+    self.ncols
+  }
+
+  fn apply(&self, x: &[f64], y: &mut [f64]) {
+    for (i, yi) in y.iter_mut().enumerate() {
+      *yi = self.row(i).iter().zip(x).map(|(aij, xj)| aij * xj).sum();
+    }
+  }
+
+  fn apply_transpose(&self, y: &[f64], x: &mut [f64]) {
+    x.fill(0.0);
+    for (i, yi) in y.iter().enumerate() {
+      x.iter_mut().zip(self.row(i)).for_each(|(xj, aij)| *xj += aij * yi);
+    }
+  }
+}
+
+/// A linear operator given by a pair of closures computing A x and A' y.
+///
+/// `apply` is called with x of length ncols and must fill y of length
+/// nrows; `apply_transpose` is called with y of length nrows and must fill
+/// x of length ncols. The closures are trusted to honor the declared sizes:
+/// neither this type nor its users such as `nnls` check what they write.
+pub struct FnOperator<F, G> {
+  nrows: usize,
+  ncols: usize,
+  apply: F,
+  apply_transpose: G,
+}
+
+impl<F, G> FnOperator<F, G>
+where
+  F: Fn(&[f64], &mut [f64]),
+  G: Fn(&[f64], &mut [f64]),
+{
+  pub fn new(nrows: usize, ncols: usize, apply: F, apply_transpose: G) -> Self {
+    Self {
+      nrows,
+      ncols,
+      apply,
+      apply_transpose,
+    }
+  }
+}
+
+impl<F, G> LinearOperator for FnOperator<F, G>
+where
+  F: Fn(&[f64], &mut [f64]),
+  G: Fn(&[f64], &mut [f64]),
+{
+  fn nrows(&self) -> usize {
+    self.nrows
+  }
+
+  fn ncols(&self) -> usize {
+    self.ncols
+  }
+
+  fn apply(&self, x: &[f64], y: &mut [f64]) {
+    (self.apply)(x, y)
+  }
+
+  fn apply_transpose(&self, y: &[f64], x: &mut [f64]) {
+    (self.apply_transpose)(y, x)
+  }
+}
+// operator:1 ends here
@@ -0,0 +1,118 @@
+// [[file:../lbfgsb.note::*progress][progress:1]]
+use std::borrow::Cow;
+
+use crate::checkpoint::Checkpoint;
+use crate::stopping::StopHistory;
+use crate::workspace::Workspace;
+
+/// The state of a minimization when setulb returns with a new iterate,
+/// passed to the observer set in `LbfgsbProblem::observer`.
+pub struct Progress<'a> {
+  /// The new iterate, in original units.
+  pub x: Cow<'a, [f64]>,
+  /// f(x) at the new iterate, unscaled.
+  pub f: f64,
+  /// g(x) at the new iterate, in original units.
+  pub g: Cow<'a, [f64]>,
+  /// The number of iterations done, summed over restarts.
+  pub niter: usize,
+  /// The number of f and g evaluations, summed over restarts.
+  pub nfg: usize,
+  /// The infinity norm of the projected gradient, as computed by setulb
+  /// for its scaled variables. With `LbfgsbProblem::select` or
+  /// `LbfgsbProblem::transforms` it is recomputed for x and g as reported.
+  pub pgnorm: f64,
+  /// The number of free variables at the generalized Cauchy point of this
+  /// iteration.
+  pub nfree: usize,
+  /// The number of variables at a bound at the generalized Cauchy point.
+  pub nactive: usize,
+  /// The number of variables that left a bound since the previous
+  /// iteration.
+  pub nfreed: usize,
+  /// The number of variables that hit a bound since the previous iteration.
+  pub nbound: usize,
+
+  // x, f, g and the bounds as seen by setulb, for checkpoints
+  pub(crate) xs: &'a [f64],
+  pub(crate) fs: f64,
+  pub(crate) gs: &'a [f64],
+  pub(crate) l: &'a [f64],
+  pub(crate) u: &'a [f64],
+  pub(crate) nbd: &'a [i64],
+  pub(crate) ws: &'a Workspace,
+  pub(crate) stop: &'a StopHistory,
+}
+
+impl<'a> Progress<'a> {
+  #[allow(clippy::too_many_arguments)]
+  pub(crate) fn new(
+    x: &'a [f64],
+    f: f64,
+    g: &'a [f64],
+    l: &'a [f64],
+    u: &'a [f64],
+    nbd: &'a [i64],
+    ws: &'a Workspace,
+    stop: &'a StopHistory,
+  ) -> Self {
+    let n = x.len();
+    // nfree, nact, ileave and nenter of mainlb in isave(38..41); freev
+    // counts changes only for constrained problems (lsave(2))
+    let (nfree, nactive) = (ws.isave[37] as usize, ws.isave[38] as usize);
+    let (nfreed, nbound) = if ws.lsave[1] != 0 && ws.isave[39] > 0 {
+      (ws.isave[40] as usize, n + 1 - ws.isave[39] as usize)
+    } else {
+      (0, 0)
+    };
+
+    Self {
+      x: ws.original_x(x),
+      f: ws.original_f(f),
+      g: ws.original_g(g),
+      niter: ws.niter(),
+      nfg: ws.nfg(),
+      // sbgnrm of mainlb, in dsave(13)
+      pgnorm: ws.dsave[12],
+      nfree,
+      nactive,
+      nfreed,
+      nbound,
+      xs: x,
+      fs: f,
+      gs: g,
+      l,
+      u,
+      nbd,
+      ws,
+      stop,
+    }
+  }
+
+  // The same progress with x, g and the projected gradient norm of the
+  // full problem.
+  pub(crate) fn with_full(&self, x: Vec<f64>, g: Vec<f64>, pgnorm: f64) -> Progress<'a> {
+    Progress {
+      x: Cow::Owned(x),
+      g: Cow::Owned(g),
+      pgnorm,
+      ..*self
+    }
+  }
+
+  /// Save the complete state, to continue the minimization later from this
+  /// iterate.
+  pub fn checkpoint(&self) -> Checkpoint {
+    Checkpoint {
+      x: self.xs.to_vec(),
+      f: self.fs,
+      g: self.gs.to_vec(),
+      l: self.l.to_vec(),
+      u: self.u.to_vec(),
+      nbd: self.nbd.to_vec(),
+      ws: self.ws.clone(),
+      stop: self.stop.clone(),
+    }
+  }
+}
+// progress:1 ends here
@@ -0,0 +1,85 @@
+// [[file:../lbfgsb.note::*qp][qp:1]]
+use anyhow::{ensure, Result};
+
+use crate::kkt::KktReport;
+use crate::memory::CorrectionPairs;
+use crate::operator::LinearOperator;
+use crate::shared::{LbfgsbParameter, LbfgsbProblem, LbfgsbResult};
+
+/// The solution of a box-constrained quadratic program.
+#[derive(Debug)]
+pub struct QpResult {
+  pub x: Vec<f64>,
+  /// f(x) = 1/2 x'Qx + c'x.
+  pub f: f64,
+  /// g(x) = Qx + c, recomputed at the final x.
+  pub g: Vec<f64>,
+  /// The optimality conditions at x with the recomputed gradient.
+  pub kkt: KktReport,
+  /// The summary of the L-BFGS-B run.
+  pub result: LbfgsbResult,
+}
+
+/// A starting point for `solve_qp`, usually the solution of a previous,
+/// closely related program.
+#[derive(Debug, Clone, Default)]
+pub struct QpWarmStart {
+  /// The initial x, projected onto the bounds by setulb.
+  pub x: Vec<f64>,
+  /// Correction pairs to start the limited memory matrix with, e.g.
+  /// `LbfgsbResult::memory` of a previous program with the same Q.
+  pub memory: Option<CorrectionPairs>,
+}
+
+impl QpResult {
+  /// A warm start from this solution.
+  pub fn warm_start(&self) -> QpWarmStart {
+    QpWarmStart {
+      x: self.x.clone(),
+      memory: Some(self.result.memory.clone()),
+    }
+  }
+}
+
+/// Minimize 1/2 x'Qx + c'x for a symmetric Q within `bounds`, one (lower,
+/// upper) pair per variable, from `warm_start` or else from x = 0.
+///
+/// Each evaluation takes one product with Q. The KKT residual is checked
+/// with the gradient recomputed at the solution.
+pub fn solve_qp<Q: LinearOperator>(
+  q: &Q,
+  c: &[f64],
+  bounds: &[(Option<f64>, Option<f64>)],
+  warm_start: Option<QpWarmStart>,
+  param: &LbfgsbParameter,
+) -> Result<QpResult> {
+  let n = c.len();
+  ensure!(q.nrows() == n && q.ncols() == n, "Q is {} x {} for {n} variables", q.nrows(), q.ncols());
+  ensure!(bounds.len() == n, "{} bounds for {n} variables", bounds.len());
+
+  let gradient = |x: &[f64], g: &mut [f64]| {
+    q.apply(x, g);
+    // f = 1/2 x'Qx + c'x = x'(1/2 Qx + c)
+    let f = x.iter().zip(g.iter()).zip(c).map(|((xi, qxi), ci)| xi * (0.5 * qxi + ci)).sum();
+    g.iter_mut().zip(c).for_each(|(gi, ci)| *gi += ci);
+    f
+  };
+  let (x0, memory) = match warm_start {
+    Some(start) => {
+      ensure!(start.x.len() == n, "warm start x has length {}, not {n}", start.x.len());
+      (start.x, start.memory)
+    }
+    None => (vec![0.0; n], None),
+  };
+  let mut problem = LbfgsbProblem::build(x0, |x: &[f64], g: &mut [f64]| Ok(gradient(x, g)));
+  problem.set_bounds(bounds.iter().copied());
+  problem.warm_start = memory;
+  let result = crate::router::lbfgsb(&mut problem, param)?;
+
+  let x = std::mem::take(&mut problem.x);
+  let mut g = vec![0.0; n];
+  let f = gradient(&x, &mut g);
+  let kkt = KktReport::new(&x, &g, &problem.l, &problem.u, &problem.nbd);
+  Ok(QpResult { x, f, g, kkt, result })
+}
+// qp:1 ends here
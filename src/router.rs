@@ -2,7 +2,10 @@ use std::sync::Mutex;
 
 use anyhow::Error;
 
-use crate::shared::{LbfgsbParameter, LbfgsbProblem};
+use crate::objective::Objective;
+use crate::shared::{LbfgsbParameter, LbfgsbProblem, LbfgsbResult};
+use crate::subset::{selected_variables, solve_subset};
+use crate::transform::solve_transformed;
 
 
 include!(concat!(env!("OUT_DIR"), "/lib.rs"));
@@ -19,8 +22,33 @@ struct LibTracker {
   pub in_use: [bool; MAX_INSTANCES],
 }
 
-pub fn lbfgsb<'a, E>(problem: &'a mut LbfgsbProblem<E>, param: &'a LbfgsbParameter) -> Result<(), Error>
-where E: FnMut(&[f64], &mut [f64]) -> Result<f64, Error> {
+/// Minimize `problem` on a free instance of the C code. Variables not
+/// selected by `LbfgsbProblem::select`, and fixed variables with
+/// `LbfgsbParameter::eliminate_fixed`, are taken out of the problem seen by
+/// setulb, and the others are transformed by `LbfgsbProblem::transforms`.
+pub fn lbfgsb<'a, E>(problem: &'a mut LbfgsbProblem<E>, param: &'a LbfgsbParameter) -> Result<LbfgsbResult, Error>
+where E: Objective {
+  match selected_variables(problem, param)? {
+    // closures, since the functions themselves are not general over the
+    // lifetime of `Gather` and `Transformed`
+    #[allow(clippy::redundant_closure)]
+    Some(index) => solve_subset(problem, param, index, |problem, param| transformed(problem, param)),
+    None => transformed(problem, param),
+  }
+}
+
+fn transformed<E>(problem: &mut LbfgsbProblem<E>, param: &LbfgsbParameter) -> Result<LbfgsbResult, Error>
+where E: Objective {
+  if problem.transforms.is_some() {
+    #[allow(clippy::redundant_closure)]
+    solve_transformed(problem, param, |problem, param| dispatch(problem, param))
+  } else {
+    dispatch(problem, param)
+  }
+}
+
+fn dispatch<E>(problem: &mut LbfgsbProblem<E>, param: &LbfgsbParameter) -> Result<LbfgsbResult, Error>
+where E: Objective {
   // Find a library that isn't currently in use...
   let mut locked = libs_in_use.lock().unwrap();
 
@@ -0,0 +1,137 @@
+// [[file:../lbfgsb.note::*scaling][scaling:1]]
+use anyhow::{ensure, Result};
+
+use crate::objective::Objective;
+use crate::shared::{LbfgsbProblem, LbfgsbResult};
+use crate::workspace::Workspace;
+
+/// Per-variable scaling x_i = s_i * y_i, so that setulb works with
+/// variables y of similar magnitude and curvature. The problem, the
+/// observer and the result all see x in original units.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Scaling {
+  /// Positive factors s_i given by the user.
+  Factors(Vec<f64>),
+  /// The width u_i - l_i of variables with both bounds, and max(|x0_i|, 1)
+  /// for the others.
+  Bounds,
+  /// s_i = 1 / sqrt(h_i) with the diagonal curvature estimate
+  /// h_i = |g_i| / max(|x0_i|, 1) from one evaluation at x0. Variables with
+  /// zero gradient are not scaled.
+  Gradient,
+}
+
+/// Scaling of the objective, so that setulb minimizes (f - offset) * factor
+/// and the relative reduction test of factr sees values of order one. f and
+/// g are reported unscaled; pgtol applies to the scaled gradient.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ObjectiveScaling {
+  /// A positive factor and an offset given by the user.
+  Fixed { factor: f64, offset: f64 },
+  /// The power of two nearest to 1 / |f(x0)|, from the first evaluation,
+  /// without offset. Scaling by a power of two is exact, so f is reported
+  /// bit for bit.
+  FirstEvaluation,
+}
+
+/// The power of two nearest to 1 / |f0|, or 1 if f0 is zero or not finite.
+pub(crate) fn objective_factor(f0: f64) -> f64 {
+  if f0 != 0.0 && f0.is_finite() {
+    let k = f0.abs().log2().round().clamp(-1000.0, 1000.0) as i32;
+    2f64.powi(-k)
+  } else {
+    1.0
+  }
+}
+
+/// Set the fixed objective scaling of `problem` in `ws`.
+pub(crate) fn set_objective_scaling<E: Objective>(problem: &LbfgsbProblem<E>, ws: &mut Workspace) -> Result<()> {
+  if let Some(ObjectiveScaling::Fixed { factor, offset }) = problem.objective_scaling {
+    ensure!(factor > 0.0 && factor.is_finite(), "invalid objective scaling factor {factor}");
+    ensure!(offset.is_finite(), "invalid objective offset {offset}");
+    ws.fscale = factor;
+    ws.foffset = offset;
+  }
+  Ok(())
+}
+
+/// The factors of `problem.scaling` at the initial x, if set.
+pub(crate) fn scale_factors<E: Objective>(problem: &mut LbfgsbProblem<E>) -> Result<Option<Vec<f64>>> {
+  let n = problem.x.len();
+  let x = &problem.x;
+  let s: Vec<f64> = match &problem.scaling {
+    None => return Ok(None),
+    Some(Scaling::Factors(s)) => s.clone(),
+    Some(Scaling::Bounds) => (0..n)
+      .map(|i| match problem.nbd[i] {
+        2 if problem.u[i] > problem.l[i] => problem.u[i] - problem.l[i],
+        _ => x[i].abs().max(1.0),
+      })
+      .collect(),
+    Some(Scaling::Gradient) => {
+      let mut g = vec![0.0; n];
+      problem.eval_fn.gradient_only(x, &mut g)?;
+      x.iter()
+        .zip(&g)
+        .map(|(xi, gi)| {
+          let h = gi.abs() / xi.abs().max(1.0);
+          if h > 0.0 && h.is_finite() {
+            1.0 / h.sqrt()
+          } else {
+            1.0
+          }
+        })
+        .collect()
+    }
+  };
+  ensure!(s.len() == n, "{} scaling factors for {n} variables", s.len());
+  ensure!(s.iter().all(|si| *si > 0.0 && si.is_finite()), "scaling factors must be positive");
+  Ok(Some(s))
+}
+
+/// The bounds l / s and u / s of the scaled variables.
+pub(crate) fn scaled_bounds(s: &[f64], l: &[f64], u: &[f64]) -> (Vec<f64>, Vec<f64>) {
+  let div = |b: &[f64]| b.iter().zip(s).map(|(bi, si)| bi / si).collect();
+  (div(l), div(u))
+}
+
+/// Transform x and g of `problem` to scaled variables y = x / s.
+pub(crate) fn to_scaled<E: Objective>(s: &[f64], problem: &mut LbfgsbProblem<E>) {
+  for (i, si) in s.iter().enumerate() {
+    problem.x[i] /= si;
+    // the gradient with respect to y
+    problem.g[i] *= si;
+  }
+}
+
+/// Transform x, f and g of `problem` and the points in `result` back to
+/// original units. Variables at a bound are put exactly at the bound.
+pub(crate) fn to_original<E: Objective>(ws: &Workspace, problem: &mut LbfgsbProblem<E>, result: Option<&mut LbfgsbResult>) {
+  let bounds = ws.scale.as_ref().map(|s| scaled_bounds(s, &problem.l, &problem.u));
+  let unscale = |x: &mut Vec<f64>, f: &mut f64, g: &mut Vec<f64>| {
+    if let (Some(s), Some((ls, us))) = (&ws.scale, &bounds) {
+      for (i, si) in s.iter().enumerate() {
+        let has_l = matches!(problem.nbd[i], 1 | 2);
+        let has_u = matches!(problem.nbd[i], 2 | 3);
+        x[i] = if has_l && x[i] <= ls[i] {
+          problem.l[i]
+        } else if has_u && x[i] >= us[i] {
+          problem.u[i]
+        } else {
+          x[i] * si
+        };
+      }
+    }
+    *f = ws.original_f(*f);
+    *g = ws.original_g(g).into_owned();
+  };
+  if let Some(best) = result.and_then(|r| r.best.as_mut()) {
+    unscale(&mut best.x, &mut best.f, &mut best.g);
+  }
+  let (mut x, mut f, mut g) = (std::mem::take(&mut problem.x), problem.f, std::mem::take(&mut problem.g));
+  unscale(&mut x, &mut f, &mut g);
+  problem.x = x;
+  problem.f = f;
+  problem.g = g;
+}
+// scaling:1 ends here
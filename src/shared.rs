@@ -1,17 +1,52 @@
 use anyhow::Result;
-mod bindings { include!(concat!(env!("OUT_DIR"), "/bindings_0.rs")); }
-use bindings::{FG, FG_END};
+
+use crate::best::BestPoint;
+use crate::cache::CacheStats;
+use crate::checkpoint::Checkpoint;
+use crate::kkt::KktReport;
+use crate::memory::CorrectionPairs;
+use crate::nonfinite::NonFinitePolicy;
+use crate::objective::Objective;
+use crate::progress::Progress;
+use crate::scaling::{ObjectiveScaling, Scaling};
+use crate::stopping::StopCriteria;
+use crate::subset::Selection;
+use crate::transform::Transform;
+use crate::timing::Timing;
+
+#[allow(dead_code)]
+pub(crate) mod bindings { include!(concat!(env!("OUT_DIR"), "/bindings_0.rs")); }
+use bindings::{CONVERGENCE, CONVERGENCE_END, FG, FG_END};
 
 // [[file:../lbfgsb.note::*util][util:1]]
 // #define IS_FG(x) ( ((x)>=FG) ?  ( ((x)<=FG_END) ? 1 : 0 ) : 0 )
 pub(crate) fn is_fg(task: i64) -> bool {
   let task = task as u32;
-  task >= FG && task <= FG_END
+  (FG..=FG_END).contains(&task)
+}
+
+// #define IS_CONVERGED(x) ( ((x)>=CONVERGENCE) ?  ( ((x)<=CONVERGENCE_END) ? 1 : 0 ) : 0 )
+pub(crate) fn is_converged(task: i64) -> bool {
+  let task = task as u32;
+  (CONVERGENCE..=CONVERGENCE_END).contains(&task)
+}
+
+pub(crate) fn dot(a: &[f64], b: &[f64]) -> f64 {
+  a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// The codes setulb terminates with in `LbfgsbResult::task`.
+pub mod task {
+  pub use super::bindings::{
+    ABNORMAL, CONV_F, CONV_GRAD, STOP_CPU, STOP_GRAD, STOP_ITER, WARNING_ROUND, WARNING_STPMAX, WARNING_STPMIN,
+    WARNING_XTOL,
+  };
 }
 // util:1 ends here
 
 // [[file:../lbfgsb.note::*param][param:1]]
 /// L-BFGS-B algorithm parameters
+#[derive(Debug, Clone)]
 pub struct LbfgsbParameter {
   /// On entry m is the maximum number of variable metric corrections allowed
   /// in the limited memory matrix.
@@ -50,6 +85,60 @@ pub struct LbfgsbParameter {
   // When iprint > 0, the file iterate.dat will be created to summarize the
   // iteration.
   pub iprint: i64,
+
+  /// Number of most recently evaluated points whose f(x) and g(x) are
+  /// remembered, so that repeated requests for the exact same x do not call
+  /// `eval_fn` again. Caching is disabled when set to 0.
+  pub cache_size: usize,
+
+  /// What to do when `eval_fn` returns a non-finite f(x) or g(x).
+  pub non_finite: NonFinitePolicy,
+
+  /// Leave the best feasible point evaluated in the problem on return,
+  /// instead of the point setulb terminated at.
+  pub return_best: bool,
+
+  /// Restart from the current iterate after an abnormal termination in the
+  /// line search.
+  pub restart: RestartPolicy,
+
+  /// The sufficient decrease (Armijo) tolerance of the Moré-Thuente line
+  /// search.
+  pub ftol: f64,
+
+  /// The curvature condition tolerance of the line search. Values closer to
+  /// 1 accept steps with less decrease of the directional derivative, which
+  /// helps with noisy objectives.
+  pub gtol: f64,
+
+  /// The relative width of the interval of uncertainty at which the line
+  /// search stops.
+  pub xtol: f64,
+
+  /// The maximum number of f and g evaluations in one line search.
+  pub maxls: usize,
+
+  /// Stopping criteria besides factr and pgtol.
+  pub stop: StopCriteria,
+
+  /// Take variables with equal lower and upper bounds out of the problem
+  /// seen by setulb, which then works on the free variables only. x and g
+  /// are still full length for `eval_fn` and on return. Only applies to
+  /// `router::lbfgsb`.
+  pub eliminate_fixed: bool,
+}
+
+/// Restarting setulb after ABNORMAL_TERMINATION_IN_LNSRCH, which can often
+/// be fixed by discarding the limited memory BFGS matrix.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RestartPolicy {
+  /// The maximum number of restarts. Restarting is disabled when set to 0.
+  pub max_restarts: usize,
+
+  /// The number of corrections `m` to use after a restart, if it should be
+  /// reduced. Values larger than `LbfgsbParameter::m` are clamped, and 0 is
+  /// rejected when the run starts.
+  pub m: Option<usize>,
 }
 
 impl Default for LbfgsbParameter {
@@ -59,15 +148,29 @@ impl Default for LbfgsbParameter {
           factr: 1E1,
           pgtol: 1E-5,
           iprint: -1,
+          cache_size: 0,
+          non_finite: NonFinitePolicy::Ignore,
+          return_best: false,
+          restart: RestartPolicy::default(),
+          ftol: 1E-3,
+          gtol: 0.9,
+          xtol: 0.1,
+          maxls: 20,
+          stop: StopCriteria::default(),
+          eliminate_fixed: false,
       }
   }
 }
 // param:1 ends here
 
 // [[file:../lbfgsb.note::*problem][problem:1]]
+/// Called with each new iterate. Returning false stops minimization with
+/// `StopReason::Observer`, returning Err aborts it.
+pub type Observer = Box<dyn FnMut(&Progress) -> Result<bool>>;
+
 pub struct LbfgsbProblem<E>
 where
-  E: FnMut(&[f64], &mut [f64]) -> Result<f64>,
+  E: Objective,
 {
   pub x: Vec<f64>,
   pub g: Vec<f64>,
@@ -76,11 +179,31 @@ where
   pub u: Vec<f64>,
   pub nbd: Vec<i64>,
   pub eval_fn: E,
+  /// Auxiliary data reported by `eval_fn` at its most recent evaluation.
+  pub aux: Option<E::Aux>,
+  /// Correction pairs from a previous run to start the limited memory
+  /// matrix with, see `LbfgsbResult::memory`.
+  pub warm_start: Option<CorrectionPairs>,
+  /// Observes the progress of minimization at each new iterate.
+  pub observer: Option<Observer>,
+  /// Scale the variables seen by setulb.
+  pub scaling: Option<Scaling>,
+  /// Scale f and g seen by setulb.
+  pub objective_scaling: Option<ObjectiveScaling>,
+  /// Optimize only some of the variables; the others keep their values.
+  /// Only applies to `router::lbfgsb`.
+  pub select: Option<Selection>,
+  /// Transform the variables seen by setulb, one per variable. Only applies
+  /// to `router::lbfgsb`.
+  pub transforms: Option<Vec<Transform>>,
+  /// A checkpoint to continue minimization from. Its x, f, g, bounds and
+  /// setulb parameters replace those of the problem and `LbfgsbParameter`.
+  pub resume: Option<Checkpoint>,
 }
 
 impl<E> LbfgsbProblem<E>
 where
-  E: FnMut(&[f64], &mut [f64]) -> Result<f64>,
+  E: Objective,
 {
   pub fn build(x: Vec<f64>, eval_fn: E) -> Self {
     let n = x.len();
@@ -92,6 +215,14 @@ where
       u: vec![0.0; n],
       nbd: vec![0; n],
       eval_fn,
+      aux: None,
+      warm_start: None,
+      observer: None,
+      scaling: None,
+      objective_scaling: None,
+      select: None,
+      transforms: None,
+      resume: None,
     }
   }
 
@@ -113,6 +244,11 @@ where
     }
   }
 
+  /// Check the optimality conditions at the current x and g(x).
+  pub fn kkt(&self) -> KktReport {
+    KktReport::new(&self.x, &self.g, &self.l, &self.u, &self.nbd)
+  }
+
   /// Set lower bounds and upper bounds for input variables
   pub fn set_bounds<B>(&mut self, bounds: B)
   where
@@ -153,3 +289,70 @@ where
 }
 // problem:1 ends here
 
+// [[file:../lbfgsb.note::*result][result:1]]
+/// Summary of a finished L-BFGS-B minimization. The final x, f(x) and g(x)
+/// are left in `LbfgsbProblem`.
+#[derive(Debug, Clone)]
+pub struct LbfgsbResult {
+  /// The task code setulb terminated with, e.g. CONV_GRAD or ABNORMAL.
+  pub task: i64,
+
+  /// The number of iterations, summed over restarts.
+  pub niter: usize,
+
+  /// The total number of f and g evaluations requested by setulb, summed
+  /// over restarts.
+  pub nfg: usize,
+
+  /// The number of restarts after an abnormal line search.
+  pub nrestart: usize,
+
+  /// Evaluation cache statistics, if `cache_size` was set.
+  pub cache: Option<CacheStats>,
+
+  /// Set if minimization was stopped by the driver rather than by setulb.
+  pub stop: Option<StopReason>,
+
+  /// The number of evaluations returning a non-finite f(x) or g(x). Only
+  /// counted when `non_finite` is not `Ignore`.
+  pub non_finite: usize,
+
+  /// The best (lowest f) feasible point evaluated, which may differ from the
+  /// final point after an abnormal termination.
+  pub best: Option<BestPoint>,
+
+  /// The correction pairs of the limited memory matrix at termination, for
+  /// warm starting a related problem.
+  pub memory: CorrectionPairs,
+
+  /// Indices of the variables at their lower bound in the final x.
+  pub at_lower: Vec<usize>,
+
+  /// Indices of the variables at their upper bound in the final x.
+  pub at_upper: Vec<usize>,
+
+  /// Time spent in `eval_fn` and in each phase of setulb.
+  pub timing: Timing,
+}
+
+/// Reasons for the driver to stop minimization before setulb terminates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+  /// `eval_fn` returned a non-finite value under `NonFinitePolicy::ReturnBest`.
+  NonFinite,
+  /// The observer returned false.
+  Observer,
+  /// f(x) reached `StopCriteria::f_target`.
+  TargetF,
+  /// x changed less than `StopCriteria::x_tol` for `x_iters` iterations.
+  XChange,
+  /// f decreased less than `StopCriteria::stagnation_tol` over
+  /// `stagnation_iters` iterations.
+  Stagnation,
+  /// The number of iterations reached `StopCriteria::max_iter`.
+  MaxIter,
+  /// The number of evaluations exceeded `StopCriteria::max_fun`.
+  MaxFun,
+}
+// result:1 ends here
+
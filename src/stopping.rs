@@ -0,0 +1,113 @@
+// [[file:../lbfgsb.note::*stopping][stopping:1]]
+use std::collections::VecDeque;
+
+use crate::shared::StopReason;
+
+/// Stopping criteria checked by the driver at each new iterate, in addition
+/// to the factr and pgtol tests of setulb. All are disabled by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StopCriteria {
+  /// Stop with `StopReason::TargetF` once f(x) <= f_target, e.g. 0 for a
+  /// zero residual fit.
+  pub f_target: Option<f64>,
+
+  /// Stop with `StopReason::XChange` when max |x_i - xold_i| / max(|x_i|, 1)
+  /// stays below x_tol for `x_iters` consecutive iterations.
+  pub x_tol: Option<f64>,
+  pub x_iters: usize,
+
+  /// Stop with `StopReason::Stagnation` when f decreased by less than
+  /// stagnation_tol * max(|f|, 1) over the last `stagnation_iters`
+  /// iterations.
+  pub stagnation_tol: Option<f64>,
+  pub stagnation_iters: usize,
+
+  /// Stop with `StopReason::MaxIter` after this many iterations.
+  pub max_iter: Option<usize>,
+
+  /// Stop with `StopReason::MaxFun` at the first iterate after more than
+  /// this many f and g evaluations, as scipy does.
+  pub max_fun: Option<usize>,
+}
+
+/// What the x-change and stagnation criteria remember of the previous
+/// iterates, kept in checkpoints so that a resumed run stops where an
+/// uninterrupted one does.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct StopHistory {
+  pub xold: Vec<f64>,
+  // consecutive iterations with a small change of x
+  pub nsmall: usize,
+  // f of the last stagnation_iters + 1 iterates
+  pub f: VecDeque<f64>,
+}
+
+/// Applies `StopCriteria` to the sequence of iterates.
+pub(crate) struct StopChecker {
+  criteria: StopCriteria,
+  history: StopHistory,
+}
+
+impl StopChecker {
+  /// Continue with `history`, empty for a new run.
+  pub fn new(criteria: StopCriteria, history: StopHistory) -> Self {
+    Self { criteria, history }
+  }
+
+  pub fn history(&self) -> &StopHistory {
+    &self.history
+  }
+
+  /// Start over from the point `x0` with value `f0`.
+  pub fn start(&mut self, x0: &[f64], f0: f64) {
+    let history = &mut self.history;
+    history.xold = x0.to_vec();
+    history.nsmall = 0;
+    history.f.clear();
+    history.f.push_back(f0);
+  }
+
+  /// Check the new iterate x with value f, reached after `niter` iterations
+  /// and `nfg` evaluations. Without a starting point, as when resuming from
+  /// a checkpoint, x becomes the starting point.
+  pub fn check(&mut self, x: &[f64], f: f64, niter: usize, nfg: usize) -> Option<StopReason> {
+    let criteria = self.criteria;
+    if criteria.max_iter.is_some_and(|max| niter >= max) {
+      return Some(StopReason::MaxIter);
+    }
+    if criteria.max_fun.is_some_and(|max| nfg > max) {
+      return Some(StopReason::MaxFun);
+    }
+    if self.history.xold.len() != x.len() {
+      self.start(x, f);
+      return criteria.f_target.is_some_and(|target| f <= target).then_some(StopReason::TargetF);
+    }
+
+    let history = &mut self.history;
+    let dx = x
+      .iter()
+      .zip(&history.xold)
+      .map(|(xi, xo)| (xi - xo).abs() / xi.abs().max(1.0))
+      .fold(0.0, f64::max);
+    history.xold.copy_from_slice(x);
+    history.nsmall = if criteria.x_tol.is_some_and(|tol| dx <= tol) { history.nsmall + 1 } else { 0 };
+
+    let iters = criteria.stagnation_iters.max(1);
+    history.f.push_back(f);
+    if history.f.len() > iters + 1 {
+      history.f.pop_front();
+    }
+
+    if criteria.f_target.is_some_and(|target| f <= target) {
+      Some(StopReason::TargetF)
+    } else if criteria.x_tol.is_some() && history.nsmall >= criteria.x_iters.max(1) {
+      Some(StopReason::XChange)
+    } else if let Some(tol) = criteria.stagnation_tol {
+      let full = history.f.len() == iters + 1;
+      (full && history.f[0] - f <= tol * f.abs().max(1.0)).then_some(StopReason::Stagnation)
+    } else {
+      None
+    }
+  }
+}
+// stopping:1 ends here
@@ -0,0 +1,288 @@
+// [[file:../lbfgsb.note::*subset][subset:1]]
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use anyhow::{bail, ensure, Result};
+
+use crate::kkt::{active_sets, projected_gradient_norm};
+use crate::memory::CorrectionPairs;
+use crate::objective::Objective;
+use crate::progress::Progress;
+use crate::scaling::Scaling;
+use crate::shared::bindings::CONV_GRAD;
+use crate::shared::{LbfgsbParameter, LbfgsbProblem, LbfgsbResult, Observer};
+use crate::timing::Timing;
+
+// x and g(x) of the full problem at the latest evaluation.
+#[derive(Debug, Default)]
+struct Full {
+  x: Vec<f64>,
+  g: Vec<f64>,
+}
+
+/// The objective as a function of the variables `index` only, with the
+/// other variables held at their values in the full x.
+pub struct Gather<'a, E> {
+  eval_fn: &'a mut E,
+  index: Vec<usize>,
+  full: Rc<RefCell<Full>>,
+}
+
+impl<E: Objective> Gather<'_, E> {
+  fn scatter(&self, y: &[f64]) {
+    let x = &mut self.full.borrow_mut().x;
+    self.index.iter().zip(y).for_each(|(&i, yi)| x[i] = *yi);
+  }
+
+  fn gather(&self, g: &mut [f64]) {
+    let full = self.full.borrow();
+    g.iter_mut().zip(&self.index).for_each(|(gi, &i)| *gi = full.g[i]);
+  }
+}
+
+impl<E: Objective> Objective for Gather<'_, E> {
+  type Aux = E::Aux;
+
+  fn evaluate(&mut self, y: &[f64], g: &mut [f64]) -> Result<f64> {
+    self.scatter(y);
+    let f = {
+      let full = &mut *self.full.borrow_mut();
+      self.eval_fn.evaluate(&full.x, &mut full.g)?
+    };
+    self.gather(g);
+    Ok(f)
+  }
+
+  fn value_only(&mut self, y: &[f64]) -> Result<f64> {
+    self.scatter(y);
+    self.eval_fn.value_only(&self.full.borrow().x)
+  }
+
+  fn gradient_only(&mut self, y: &[f64], g: &mut [f64]) -> Result<()> {
+    self.scatter(y);
+    {
+      let full = &mut *self.full.borrow_mut();
+      self.eval_fn.gradient_only(&full.x, &mut full.g)?;
+    }
+    self.gather(g);
+    Ok(())
+  }
+
+  fn aux(&mut self) -> Option<Self::Aux> {
+    self.eval_fn.aux()
+  }
+}
+
+/// The variables to optimize, set in `LbfgsbProblem::select`. The others
+/// keep their values in x, while `eval_fn` still sees the full vector.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Selection {
+  /// Optimize the variables whose mask entry is true.
+  Mask(Vec<bool>),
+  /// Optimize the variables with these indices.
+  Indices(Vec<usize>),
+}
+
+/// The indices of the variables setulb works on, if not all of them: those
+/// selected by `problem.select`, without fixed variables if
+/// `param.eliminate_fixed` is set. Fixed variables are put at their bound.
+/// The indices are empty if all selected variables are fixed.
+pub(crate) fn selected_variables<E: Objective>(
+  problem: &mut LbfgsbProblem<E>,
+  param: &LbfgsbParameter,
+) -> Result<Option<Vec<usize>>> {
+  let n = problem.x.len();
+  let mut selected = match &problem.select {
+    None => vec![true; n],
+    Some(Selection::Mask(mask)) => {
+      ensure!(mask.len() == n, "mask of length {} for {n} variables", mask.len());
+      mask.clone()
+    }
+    Some(Selection::Indices(index)) => {
+      let mut selected = vec![false; n];
+      for &i in index {
+        ensure!(i < n, "variable index {i} out of range for {n} variables");
+        selected[i] = true;
+      }
+      selected
+    }
+  };
+  if problem.select.is_some() && !selected.contains(&true) {
+    bail!("no variables selected");
+  }
+  if param.eliminate_fixed {
+    for (i, selected) in selected.iter_mut().enumerate() {
+      if problem.nbd[i] == 2 && problem.l[i] == problem.u[i] {
+        problem.x[i] = problem.l[i];
+        *selected = false;
+      }
+    }
+  }
+
+  let index: Vec<usize> = (0..n).filter(|&i| selected[i]).collect();
+  if index.is_empty() && problem.select.is_none() {
+    // all variables are fixed; leave them to setulb
+    return Ok(None);
+  }
+  Ok((index.len() < n).then_some(index))
+}
+
+// The observer of `problem`, to be shared with the wrapper seeing the
+// reduced or transformed problem.
+pub(crate) fn share_observer<E: Objective>(problem: &mut LbfgsbProblem<E>) -> Option<Rc<RefCell<Observer>>> {
+  problem.observer.take().map(|observer| Rc::new(RefCell::new(observer)))
+}
+
+// An observer passing on the progress with x and g of the outer problem
+// `problem`, computed by `outer`, and the projected gradient norm within its
+// bounds.
+pub(crate) fn wrap_observer<E, F>(observer: &Option<Rc<RefCell<Observer>>>, problem: &LbfgsbProblem<E>, outer: F) -> Option<Observer>
+where
+  E: Objective,
+  F: Fn(&Progress) -> (Vec<f64>, Vec<f64>) + 'static,
+{
+  observer.as_ref().map(|observer| {
+    let observer = observer.clone();
+    let (l, u, nbd) = (problem.l.clone(), problem.u.clone(), problem.nbd.clone());
+    Box::new(move |progress: &Progress| {
+      let (x, g) = outer(progress);
+      let pgnorm = projected_gradient_norm(&x, &g, &l, &u, &nbd);
+      (observer.borrow_mut())(&progress.with_full(x, g, pgnorm))
+    }) as Observer
+  })
+}
+
+// The observer back, once the wrapper is dropped.
+pub(crate) fn unshare_observer(observer: Option<Rc<RefCell<Observer>>>) -> Option<Observer> {
+  observer.and_then(|observer| Rc::try_unwrap(observer).ok()).map(RefCell::into_inner)
+}
+
+/// Minimize over the variables `index` of `problem` only, by calling `run`
+/// on the reduced problem, and put the full x, f and g back into `problem`.
+/// Without any variables, `problem` is left at the current point.
+/// The other variables keep their values in `problem.x`, and their gradient
+/// in `problem.g` and in the best point is that of the latest evaluation,
+/// which differs from g(x) if the final x was not evaluated last, as after
+/// an abnormal line search or with `return_best`.
+///
+/// The observer sees the full x and g and their projected gradient norm,
+/// with the gradient of the other variables from the latest evaluation. Checkpoints taken by the observer
+/// hold the reduced problem, and resume a run over the same subset.
+pub(crate) fn solve_subset<E, R>(
+  problem: &mut LbfgsbProblem<E>,
+  param: &LbfgsbParameter,
+  index: Vec<usize>,
+  run: R,
+) -> Result<LbfgsbResult>
+where
+  E: Objective,
+  R: FnOnce(&mut LbfgsbProblem<Gather<E>>, &LbfgsbParameter) -> Result<LbfgsbResult>,
+{
+  let n = problem.x.len();
+  if index.is_empty() {
+    // all selected variables are fixed: nothing to minimize, and the
+    // projected gradient over them is zero
+    let (at_lower, at_upper) = active_sets(&problem.x, &problem.l, &problem.u, &problem.nbd);
+    return Ok(LbfgsbResult {
+      task: CONV_GRAD.into(),
+      niter: 0,
+      nfg: 0,
+      nrestart: 0,
+      cache: None,
+      stop: None,
+      non_finite: 0,
+      best: None,
+      memory: CorrectionPairs::default(),
+      at_lower,
+      at_upper,
+      timing: Timing::default(),
+    });
+  }
+  let pick = |v: &[f64]| -> Vec<f64> { index.iter().map(|&i| v[i]).collect() };
+  let full = Rc::new(RefCell::new(Full {
+    x: problem.x.clone(),
+    g: problem.g.clone(),
+  }));
+
+  let observer = share_observer(problem);
+  let wrapped = {
+    let (full, index) = (full.clone(), index.clone());
+    wrap_observer(&observer, problem, move |progress| {
+      let (mut x, mut g) = {
+        let full = full.borrow();
+        (full.x.clone(), full.g.clone())
+      };
+      for (k, &i) in index.iter().enumerate() {
+        x[i] = progress.x[k];
+        g[i] = progress.g[k];
+      }
+      (x, g)
+    })
+  };
+
+  let mut reduced = LbfgsbProblem {
+    x: pick(&problem.x),
+    g: pick(&problem.g),
+    f: problem.f,
+    l: pick(&problem.l),
+    u: pick(&problem.u),
+    nbd: index.iter().map(|&i| problem.nbd[i]).collect(),
+    eval_fn: Gather {
+      eval_fn: &mut problem.eval_fn,
+      index: index.clone(),
+      full: full.clone(),
+    },
+    aux: None,
+    warm_start: problem.warm_start.as_ref().map(|pairs| match pairs.s.first() {
+      Some(s) if s.len() == n => pairs.gather(&index),
+      _ => pairs.clone(),
+    }),
+    observer: wrapped,
+    scaling: match &problem.scaling {
+      Some(Scaling::Factors(s)) if s.len() == n => Some(Scaling::Factors(pick(s))),
+      scaling => scaling.clone(),
+    },
+    transforms: problem
+      .transforms
+      .as_ref()
+      .map(|t| if t.len() == n { index.iter().map(|&i| t[i]).collect() } else { t.clone() }),
+    objective_scaling: problem.objective_scaling,
+    select: None,
+    resume: problem.resume.take(),
+  };
+  let result = run(&mut reduced, param);
+
+  // the full point, without another evaluation: the gradient of the other
+  // variables is from the latest evaluation
+  let mut x = problem.x.clone();
+  let mut g = std::mem::take(&mut full.borrow_mut().g);
+  for (k, &i) in index.iter().enumerate() {
+    x[i] = reduced.x[k];
+    g[i] = reduced.g[k];
+  }
+  let (f, aux) = (reduced.f, reduced.aux.take());
+  drop(reduced);
+
+  problem.x = x;
+  problem.f = f;
+  problem.g = g;
+  if aux.is_some() {
+    problem.aux = aux;
+  }
+  problem.observer = unshare_observer(observer);
+
+  let mut result = result?;
+  result.memory = result.memory.scatter(&index, n);
+  if let Some(best) = &mut result.best {
+    let (mut x, mut g) = (problem.x.clone(), problem.g.clone());
+    for (k, &i) in index.iter().enumerate() {
+      x[i] = best.x[k];
+      g[i] = best.g[k];
+    }
+    best.x = x;
+    best.g = g;
+  }
+  (result.at_lower, result.at_upper) = active_sets(&problem.x, &problem.l, &problem.u, &problem.nbd);
+  Ok(result)
+}
+// subset:1 ends here
@@ -0,0 +1,39 @@
+// [[file:../lbfgsb.note::*timing][timing:1]]
+use std::time::Duration;
+
+/// Where the time of a minimization went.
+///
+/// `objective` and `solver` are wall clock times measured around the calls
+/// to `eval_fn` and setulb in this run. The phases of setulb come from its
+/// internal timers, which measure process CPU time with clock(), and are
+/// summed over restarts and over the part of a run before its checkpoint.
+/// The line search timer runs while setulb waits for f and g, so
+/// `line_search` includes the CPU time of evaluations in the line search.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Timing {
+  /// Time spent evaluating f and g, including cache lookups.
+  pub objective: Duration,
+  /// Time spent inside setulb.
+  pub solver: Duration,
+  /// Time setulb spent searching for Cauchy points.
+  pub cauchy: Duration,
+  /// Time setulb spent in subspace minimization.
+  pub subspace: Duration,
+  /// Time setulb spent in line search.
+  pub line_search: Duration,
+}
+
+impl Timing {
+  /// Add the times accumulated by setulb since START, kept in dsave(7..9).
+  /// Values that are no valid duration, as from a corrupted checkpoint,
+  /// count as zero.
+  pub(crate) fn add_phases(&mut self, dsave: &[f64]) {
+    let add = |total: &mut Duration, t: f64| {
+      *total = total.saturating_add(Duration::try_from_secs_f64(t.max(0.0)).unwrap_or_default());
+    };
+    add(&mut self.cauchy, dsave[6]);
+    add(&mut self.subspace, dsave[7]);
+    add(&mut self.line_search, dsave[8]);
+  }
+}
+// timing:1 ends here
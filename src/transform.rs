@@ -0,0 +1,302 @@
+// [[file:../lbfgsb.note::*transform][transform:1]]
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use anyhow::{ensure, Result};
+
+use crate::kkt::active_sets;
+use crate::objective::Objective;
+use crate::shared::{LbfgsbParameter, LbfgsbProblem, LbfgsbResult};
+use crate::subset::{share_observer, unshare_observer, wrap_observer};
+
+/// A map x = T(y) between a variable x in natural units and the variable y
+/// seen by setulb, set per variable in `LbfgsbProblem::transforms`. The
+/// bounds of x are mapped to bounds of y, and x, g and the observer's
+/// progress are reported in natural units.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Transform {
+  /// x = y.
+  Identity,
+  /// x = exp(y), for positive x. A lower bound l <= 0 is dropped; an upper
+  /// bound must be positive.
+  Log,
+  /// x = l + (u - l) / (1 + exp(-y)) for a variable with bounds l < u,
+  /// leaving y unbounded. x never reaches its bounds exactly.
+  Logit,
+  /// x = scale * y + offset, with scale != 0.
+  Affine { scale: f64, offset: f64 },
+}
+
+// The transform of one variable with natural bounds l and u, and the
+// bounds of y together with the natural bounds they map to.
+#[derive(Debug, Clone, Copy)]
+struct Map {
+  transform: Transform,
+  l: f64,
+  u: f64,
+  lower: Option<(f64, f64)>,
+  upper: Option<(f64, f64)>,
+}
+
+impl Map {
+  fn new(transform: Transform, l: f64, u: f64, nbd: i64) -> Result<Self> {
+    let has_l = matches!(nbd, 1 | 2);
+    let has_u = matches!(nbd, 2 | 3);
+    let (lower, upper) = match transform {
+      Transform::Identity => (has_l.then_some((l, l)), has_u.then_some((u, u))),
+      Transform::Log => {
+        ensure!(!has_u || u > 0.0, "log transform with upper bound {u}");
+        ((has_l && l > 0.0).then(|| (l.ln(), l)), has_u.then(|| (u.ln(), u)))
+      }
+      Transform::Logit => {
+        ensure!(nbd == 2 && l < u, "logit transform without bounds l < u");
+        (None, None)
+      }
+      Transform::Affine { scale, offset } => {
+        ensure!(scale != 0.0 && scale.is_finite() && offset.is_finite(), "invalid affine transform");
+        let yl = has_l.then(|| ((l - offset) / scale, l));
+        let yu = has_u.then(|| ((u - offset) / scale, u));
+        if scale > 0.0 {
+          (yl, yu)
+        } else {
+          (yu, yl)
+        }
+      }
+    };
+    Ok(Self { transform, l, u, lower, upper })
+  }
+
+  fn y(&self, x: f64) -> Result<f64> {
+    Ok(match self.transform {
+      Transform::Identity => x,
+      Transform::Log => {
+        ensure!(x > 0.0, "log transform of x = {x}");
+        x.ln()
+      }
+      Transform::Logit => {
+        ensure!(x > self.l && x < self.u, "logit transform of x = {x} not within its bounds");
+        let p = (x - self.l) / (self.u - self.l);
+        (p / (1.0 - p)).ln()
+      }
+      Transform::Affine { scale, offset } => (x - offset) / scale,
+    })
+  }
+
+  // x for y, exactly at the bound when y is at a bound
+  fn x(&self, y: f64) -> f64 {
+    match (self.lower, self.upper) {
+      (Some((yl, xl)), _) if y <= yl => xl,
+      (_, Some((yu, xu))) if y >= yu => xu,
+      _ => match self.transform {
+        Transform::Identity => y,
+        Transform::Log => y.exp(),
+        Transform::Logit => self.l + (self.u - self.l) / (1.0 + (-y).exp()),
+        Transform::Affine { scale, offset } => scale * y + offset,
+      },
+    }
+  }
+
+  fn dxdy(&self, x: f64) -> f64 {
+    match self.transform {
+      Transform::Identity => 1.0,
+      Transform::Log => x,
+      Transform::Logit => (x - self.l) * (self.u - x) / (self.u - self.l),
+      Transform::Affine { scale, .. } => scale,
+    }
+  }
+}
+
+// x and g(x) in natural units at the latest evaluation, and at the
+// evaluation with the lowest finite f.
+#[derive(Debug, Default)]
+struct Natural {
+  x: Vec<f64>,
+  g: Vec<f64>,
+  best: Option<(f64, Vec<f64>, Vec<f64>)>,
+}
+
+impl Natural {
+  // g(x) as evaluated, if x is the latest or the best point
+  fn evaluated(&self, x: &[f64]) -> Option<&[f64]> {
+    match &self.best {
+      _ if self.x == x => Some(&self.g),
+      Some((_, bx, bg)) if bx == x => Some(bg),
+      _ => None,
+    }
+  }
+
+  fn update_best(&mut self, f: f64) {
+    let lower = self.best.as_ref().is_none_or(|(fb, _, _)| f < *fb);
+    if lower && f.is_finite() && self.g.iter().all(|gi| gi.is_finite()) {
+      self.best = Some((f, self.x.clone(), self.g.clone()));
+    }
+  }
+}
+
+/// The objective as a function of the transformed variables y.
+pub struct Transformed<'a, E> {
+  eval_fn: &'a mut E,
+  maps: Rc<[Map]>,
+  natural: Rc<RefCell<Natural>>,
+  // x of value-only evaluations, which leave `natural` alone
+  scratch: Vec<f64>,
+}
+
+impl<E: Objective> Transformed<'_, E> {
+  fn to_natural(&self, y: &[f64], x: &mut [f64]) {
+    x.iter_mut().zip(y).zip(self.maps.iter()).for_each(|((xi, yi), map)| *xi = map.x(*yi));
+  }
+
+  // the chain rule g_y = g_x dx/dy
+  fn to_transformed(&self, g: &mut [f64]) {
+    let natural = self.natural.borrow();
+    for (i, map) in self.maps.iter().enumerate() {
+      g[i] = natural.g[i] * map.dxdy(natural.x[i]);
+    }
+  }
+}
+
+impl<E: Objective> Objective for Transformed<'_, E> {
+  type Aux = E::Aux;
+
+  fn evaluate(&mut self, y: &[f64], g: &mut [f64]) -> Result<f64> {
+    let f = {
+      let natural = &mut *self.natural.borrow_mut();
+      self.to_natural(y, &mut natural.x);
+      let f = self.eval_fn.evaluate(&natural.x, &mut natural.g)?;
+      natural.update_best(f);
+      f
+    };
+    self.to_transformed(g);
+    Ok(f)
+  }
+
+  fn value_only(&mut self, y: &[f64]) -> Result<f64> {
+    // leaves the latest evaluation of g alone
+    let mut x = std::mem::take(&mut self.scratch);
+    self.to_natural(y, &mut x);
+    let f = self.eval_fn.value_only(&x);
+    self.scratch = x;
+    f
+  }
+
+  fn gradient_only(&mut self, y: &[f64], g: &mut [f64]) -> Result<()> {
+    {
+      let natural = &mut *self.natural.borrow_mut();
+      self.to_natural(y, &mut natural.x);
+      self.eval_fn.gradient_only(&natural.x, &mut natural.g)?;
+    }
+    self.to_transformed(g);
+    Ok(())
+  }
+
+  fn aux(&mut self) -> Option<Self::Aux> {
+    self.eval_fn.aux()
+  }
+}
+
+// The natural gradient at x for the gradient g with respect to y: g(x) as
+// evaluated if known, else g / dx/dy. Where dx/dy vanishes, as for a
+// saturated logit, g is from the latest evaluation.
+fn natural_gradient(maps: &[Map], natural: &Natural, x: &[f64], g: &[f64]) -> Vec<f64> {
+  if let Some(gx) = natural.evaluated(x) {
+    return gx.to_vec();
+  }
+  let dxdy = maps.iter().zip(x).map(|(map, xi)| map.dxdy(*xi));
+  dxdy.zip(g).zip(&natural.g).map(|((d, gi), gl)| if d != 0.0 { gi / d } else { *gl }).collect()
+}
+
+/// Minimize `problem` over the variables y of `problem.transforms` by
+/// calling `run` on the transformed problem, and put x, f and g in natural
+/// units back into `problem`.
+///
+/// The gradient reported at the final and the best point is g(x) as
+/// evaluated there. The correction pairs of the result and of checkpoints
+/// taken by the observer are in the transformed variables.
+pub(crate) fn solve_transformed<E, R>(problem: &mut LbfgsbProblem<E>, param: &LbfgsbParameter, run: R) -> Result<LbfgsbResult>
+where
+  E: Objective,
+  R: FnOnce(&mut LbfgsbProblem<Transformed<E>>, &LbfgsbParameter) -> Result<LbfgsbResult>,
+{
+  let n = problem.x.len();
+  let transforms = problem.transforms.clone().unwrap_or_default();
+  ensure!(transforms.len() == n, "{} transforms for {n} variables", transforms.len());
+  let maps = (0..n)
+    .map(|i| Map::new(transforms[i], problem.l[i], problem.u[i], problem.nbd[i]))
+    .collect::<Result<Vec<_>>>()?;
+  let y = maps.iter().zip(&problem.x).map(|(map, xi)| map.y(*xi)).collect::<Result<Vec<_>>>()?;
+  let bound = |b: Option<(f64, f64)>| b.map_or(0.0, |(y, _)| y);
+  let nbd = maps
+    .iter()
+    .map(|map| match (map.lower, map.upper) {
+      (None, None) => 0,
+      (Some(_), None) => 1,
+      (Some(_), Some(_)) => 2,
+      (None, Some(_)) => 3,
+    })
+    .collect();
+  let maps: Rc<[Map]> = maps.into();
+  let natural = Rc::new(RefCell::new(Natural {
+    x: problem.x.clone(),
+    g: problem.g.clone(),
+    best: None,
+  }));
+
+  let observer = share_observer(problem);
+  let wrapped = {
+    let (maps, natural) = (maps.clone(), natural.clone());
+    wrap_observer(&observer, problem, move |progress| {
+      let x: Vec<f64> = maps.iter().zip(progress.x.iter()).map(|(map, yi)| map.x(*yi)).collect();
+      let g = natural_gradient(&maps, &natural.borrow(), &x, &progress.g);
+      (x, g)
+    })
+  };
+
+  let mut transformed = LbfgsbProblem {
+    g: vec![0.0; n],
+    f: problem.f,
+    l: maps.iter().map(|map| bound(map.lower)).collect(),
+    u: maps.iter().map(|map| bound(map.upper)).collect(),
+    x: y,
+    nbd,
+    eval_fn: Transformed {
+      eval_fn: &mut problem.eval_fn,
+      maps: maps.clone(),
+      natural: natural.clone(),
+      scratch: vec![0.0; n],
+    },
+    aux: None,
+    warm_start: problem.warm_start.clone(),
+    observer: wrapped,
+    scaling: problem.scaling.clone(),
+    objective_scaling: problem.objective_scaling,
+    select: None,
+    transforms: None,
+    resume: problem.resume.take(),
+  };
+  let result = run(&mut transformed, param);
+
+  // x and g at the final point in natural units, without another
+  // evaluation
+  let x: Vec<f64> = maps.iter().zip(&transformed.x).map(|(map, yi)| map.x(*yi)).collect();
+  let g = natural_gradient(&maps, &natural.borrow(), &x, &transformed.g);
+  let (f, aux) = (transformed.f, transformed.aux.take());
+  drop(transformed);
+
+  problem.x = x;
+  problem.f = f;
+  problem.g = g;
+  if aux.is_some() {
+    problem.aux = aux;
+  }
+  problem.observer = unshare_observer(observer);
+
+  let mut result = result?;
+  if let Some(best) = &mut result.best {
+    best.x = maps.iter().zip(&best.x).map(|(map, yi)| map.x(*yi)).collect();
+    best.g = natural_gradient(&maps, &natural.borrow(), &best.x, &best.g);
+  }
+  (result.at_lower, result.at_upper) = active_sets(&problem.x, &problem.l, &problem.u, &problem.nbd);
+  Ok(result)
+}
+// transform:1 ends here
@@ -0,0 +1,149 @@
+// [[file:../lbfgsb.note::*uncertainty][uncertainty:1]]
+use anyhow::{bail, ensure, Result};
+
+use crate::kkt::{bound_status, BoundStatus};
+use crate::objective::Objective;
+use crate::shared::{dot, is_converged, LbfgsbProblem, LbfgsbResult};
+use crate::transform::Transform;
+
+/// Where the Hessian at the solution comes from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HessianSource {
+  /// The limited memory BFGS matrix of the run. Costs no evaluations, but
+  /// only approximates the Hessian along the directions explored by the last
+  /// m steps. Not available for runs with `LbfgsbProblem::transforms`.
+  Memory,
+  /// Central differences of g(x) with relative step `h`, costing 2 gradient
+  /// evaluations per free variable. `eval_fn` must be defined up to h
+  /// beyond the bounds.
+  FiniteDifference(f64),
+}
+
+/// Standard errors and covariance of the free variables at a solution.
+///
+/// When f is a negative log-likelihood, the inverse of its Hessian with
+/// respect to the free variables is the asymptotic covariance of the
+/// estimates. Variables pinned at a bound are excluded.
+#[derive(Debug, Clone)]
+pub struct Uncertainty {
+  /// Indices of the free variables, in the order of `covariance`.
+  pub free: Vec<usize>,
+  /// The covariance matrix of the free variables, as rows.
+  pub covariance: Vec<Vec<f64>>,
+  /// Standard error of each variable, None if it is pinned at a bound.
+  pub std_errors: Vec<Option<f64>>,
+}
+
+/// Estimate parameter uncertainty at the solution left in `problem` by the
+/// run summarized in `result`, which must have converged.
+///
+/// The covariance is with respect to x. `HessianSource::Memory` takes the
+/// variable scaling of the run into account, but fails if `problem` has
+/// transforms other than the identity, as the correction pairs are then in
+/// the transformed variables; use `HessianSource::FiniteDifference` there.
+pub fn estimate<E: Objective>(
+  problem: &mut LbfgsbProblem<E>,
+  result: &LbfgsbResult,
+  source: HessianSource,
+) -> Result<Uncertainty> {
+  ensure!(is_converged(result.task), "minimization has not converged (task = {})", result.task);
+  let n = problem.x.len();
+  let p = &*problem;
+  let free: Vec<_> = (0..n)
+    .filter(|&i| bound_status(p.x[i], p.l[i], p.u[i], p.nbd[i]) == BoundStatus::Free)
+    .collect();
+
+  let hessian = match source {
+    HessianSource::Memory => {
+      let transformed = p.transforms.as_ref().is_some_and(|t| t.iter().any(|ti| *ti != Transform::Identity));
+      ensure!(!transformed, "the limited memory matrix is in the transformed variables");
+      let h = result.memory.inverse_hessian(n).to_dense();
+      if free.len() == n {
+        // already the inverse of the Hessian
+        return Ok(Uncertainty::new(n, free, h));
+      }
+      let b = match inverse_spd(&h) {
+        Some(b) => b,
+        None => bail!("the limited memory matrix is not positive definite"),
+      };
+      free.iter().map(|&i| free.iter().map(|&j| b[i][j]).collect()).collect()
+    }
+    HessianSource::FiniteDifference(h) => fd_hessian(problem, &free, h)?,
+  };
+
+  match inverse_spd(&hessian) {
+    Some(covariance) => Ok(Uncertainty::new(n, free, covariance)),
+    None => bail!("the Hessian of the free variables is not positive definite"),
+  }
+}
+
+impl Uncertainty {
+  fn new(n: usize, free: Vec<usize>, covariance: Vec<Vec<f64>>) -> Self {
+    let mut std_errors = vec![None; n];
+    for (k, &i) in free.iter().enumerate() {
+      std_errors[i] = Some(covariance[k][k].sqrt());
+    }
+    Self { free, covariance, std_errors }
+  }
+}
+
+// The Hessian block of the free variables by central differences of g(x),
+// symmetrized.
+fn fd_hessian<E: Objective>(problem: &mut LbfgsbProblem<E>, free: &[usize], h: f64) -> Result<Vec<Vec<f64>>> {
+  let n = problem.x.len();
+  let mut x = problem.x.clone();
+  let mut gp = vec![0.0; n];
+  let mut gm = vec![0.0; n];
+  let k = free.len();
+  // column c in hess[c * k..]
+  let mut hess = vec![0.0; k * k];
+  for (c, &j) in free.iter().enumerate() {
+    let xj = x[j];
+    let hj = h * xj.abs().max(1.0);
+    x[j] = xj + hj;
+    problem.eval_fn.gradient_only(&x, &mut gp)?;
+    x[j] = xj - hj;
+    problem.eval_fn.gradient_only(&x, &mut gm)?;
+    x[j] = xj;
+    for (h, &i) in hess[c * k..].iter_mut().zip(free) {
+      *h = (gp[i] - gm[i]) / (2.0 * hj);
+    }
+  }
+  let rows = (0..k).map(|r| (0..k).map(|c| 0.5 * (hess[r * k + c] + hess[c * k + r])).collect());
+  Ok(rows.collect())
+}
+
+// Invert a symmetric positive definite matrix through its Cholesky factor
+// L L'. Returns None if it is not positive definite.
+fn inverse_spd(a: &[Vec<f64>]) -> Option<Vec<Vec<f64>>> {
+  let n = a.len();
+  // L in row-major order
+  let mut l = vec![0.0; n * n];
+  for j in 0..n {
+    let d = a[j][j] - dot(&l[j * n..][..j], &l[j * n..][..j]);
+    if d.is_nan() || d <= 0.0 {
+      return None;
+    }
+    l[j * n + j] = d.sqrt();
+    for i in j + 1..n {
+      l[i * n + j] = (a[i][j] - dot(&l[i * n..][..j], &l[j * n..][..j])) / l[j * n + j];
+    }
+  }
+
+  // solve L L' x = e_j for each column j; the inverse is symmetric, so
+  // column j is row j
+  let mut inv = vec![vec![0.0; n]; n];
+  let mut y = vec![0.0; n];
+  for (j, x) in inv.iter_mut().enumerate() {
+    for i in 0..n {
+      let e = if i == j { 1.0 } else { 0.0 };
+      y[i] = (e - dot(&l[i * n..][..i], &y[..i])) / l[i * n + i];
+    }
+    for i in (0..n).rev() {
+      let s: f64 = (i + 1..n).map(|k| l[k * n + i] * x[k]).sum();
+      x[i] = (y[i] - s) / l[i * n + i];
+    }
+  }
+  Some(inv)
+}
+// uncertainty:1 ends here
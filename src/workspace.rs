@@ -0,0 +1,265 @@
+// [[file:../lbfgsb.note::*workspace][workspace:1]]
+use std::borrow::Cow;
+
+use crate::shared::bindings::START;
+use crate::shared::LbfgsbParameter;
+
+/// The working arrays setulb keeps between calls, with the driver's own
+/// counters. Together with x, f(x) and g(x) this is the complete state of a
+/// minimization.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Workspace {
+  /// wa is a double precision working array of length:
+  ///   (2mmax + 5)nmax + 12mmax^2 + 12mmax.
+  pub wa: Vec<f64>,
+
+  // iwa is an integer working array of length 3nmax.
+  pub iwa: Vec<i64>,
+
+  // csave is a working string of characters of length 60.
+  // static char csave[60];
+  pub csave: [i64; 60],
+  // static double dsave[29];
+  //  dsave is a double precision working array of dimension 29.
+  // On exit with 'task' = NEW_X, the following information is
+  //                                                       available:
+  //   dsave(1) = current 'theta' in the BFGS matrix;
+  //   dsave(2) = f(x) in the previous iteration;
+  //   dsave(3) = factr*epsmch;
+  //   dsave(4) = 2-norm of the line search direction vector;
+  //   dsave(5) = the machine precision epsmch generated by the code;
+  //   dsave(7) = the accumulated time spent on searching for
+  //                                                   Cauchy points;
+  //   dsave(8) = the accumulated time spent on
+  //                                           subspace minimization;
+  //   dsave(9) = the accumulated time spent on line search;
+  //   dsave(11) = the slope of the line search function at
+  //                            the current point of line search;
+  //   dsave(12) = the maximum relative step length imposed in
+  //                                                     line search;
+  //   dsave(13) = the infinity norm of the projected gradient;
+  //   dsave(14) = the relative step length in the line search;
+  //   dsave(15) = the slope of the line search function at
+  //                           the starting point of the line search;
+  //   dsave(16) = the square of the 2-norm of the line search
+  //                                                direction vector.
+  pub dsave: [f64; 29],
+
+  // isave is an integer working array of dimension 44.
+  //   On exit with 'task' = NEW_X, the following information is
+  //                                                         available:
+  //     isave(22) = the total number of intervals explored in the
+  //                     search of Cauchy points;
+  //     isave(26) = the total number of skipped BFGS updates before
+  //                     the current iteration;
+  //     isave(30) = the number of current iteration;
+  //     isave(31) = the total number of BFGS updates prior the current
+  //                     iteration;
+  //     isave(33) = the number of intervals explored in the search of
+  //                     Cauchy point in the current iteration;
+  //     isave(34) = the total number of function and gradient
+  //                     evaluations;
+  //     isave(36) = the number of function value or gradient
+  //                              evaluations in the current iteration;
+  //     if isave(37) = 0  then the subspace argmin is within the box;
+  //     if isave(37) = 1  then the subspace argmin is beyond the box;
+  //     isave(38) = the number of free variables in the current
+  //                     iteration;
+  //     isave(39) = the number of active constraints in the current
+  //                     iteration;
+  //     n + 1 - isave(40) = the number of variables leaving the set of
+  //                       active constraints in the current iteration;
+  //     isave(41) = the number of variables entering the set of active
+  //                     constraints in the current iteration.
+  pub isave: [i64; 44],
+  // static logical lsave[4];
+  // lsave is a logical working array of dimension 4. On exit with 'task' =
+  // NEW_X, the following information is available:
+  //
+  //   If lsave(1) = .true. then the initial X has been replaced by its
+  //   projection in the feasible set;
+  //
+  //   If lsave(2) = .true.  then  the problem is constrained;
+  //
+  //   If lsave(3) = .true. then each variable has upper and lower bounds;
+  pub lsave: [i64; 4],
+
+  // Note in original fortran version:
+  //
+  // task is a working string of characters of length 60 indicating
+  // the current job when entering and leaving this subroutine.
+  //
+  // Note in L-BFGS-B-C
+  //
+  // Modified L-BFGS-B to use integers instead of strings, for testing the
+  // "task"
+  pub task: i64,
+
+  // The setulb parameters in use. `m` may be reduced on restart, `mmax` is
+  // the m the working arrays were allocated for.
+  pub m: usize,
+  pub mmax: usize,
+  pub factr: f64,
+  pub pgtol: f64,
+  // the line search parameters, set in the globals of the instance
+  pub ftol: f64,
+  pub gtol: f64,
+  pub xtol: f64,
+  pub maxls: usize,
+
+  // setulb works with y = x / scale, if set
+  pub scale: Option<Vec<f64>>,
+  // setulb minimizes (f - foffset) * fscale
+  pub fscale: f64,
+  pub foffset: f64,
+
+  // counts accumulated over restarts
+  pub nrestart: usize,
+  pub niter: usize,
+  pub nfg: usize,
+  // a warm start begins at iteration 1
+  pub iter_offset: usize,
+}
+
+impl Workspace {
+  pub fn new(n: usize, param: &LbfgsbParameter) -> Self {
+    let m = param.m;
+    let wa = vec![0.0; Self::wa_len(n, m).expect("workspace size overflow")];
+
+    // iwa is an integer working array of length 3nmax.
+    let iwa = vec![0; 3 * n];
+
+    Self {
+      wa,
+      iwa,
+      csave: [0; 60],
+      dsave: [0.0; 29],
+      isave: [0; 44],
+      lsave: [0; 4],
+      task: START.into(),
+      m,
+      mmax: m,
+      factr: param.factr,
+      pgtol: param.pgtol,
+      ftol: param.ftol,
+      gtol: param.gtol,
+      xtol: param.xtol,
+      maxls: param.maxls,
+      scale: None,
+      fscale: 1.0,
+      foffset: 0.0,
+      nrestart: 0,
+      niter: 0,
+      nfg: 0,
+      iter_offset: 0,
+    }
+  }
+
+  /// The length of wa for n variables and m corrections, None on
+  /// overflow.
+  pub fn wa_len(n: usize, m: usize) -> Option<usize> {
+    // wa is a double precision working array of length
+    //   (2mmax + 5)nmax + 12mmax^2 + 12mmax.
+    let mn = m.checked_mul(n)?;
+    let mm = m.checked_mul(m)?;
+    mn.checked_mul(2)?
+      .checked_add(n.checked_mul(5)?)?
+      .checked_add(mm.checked_mul(11)?)?
+      .checked_add(m.checked_mul(8)?)
+  }
+
+  /// Check that the offsets into wa and the memory indices setulb saved in
+  /// isave are those it sets up for n variables and the current m, so that
+  /// setulb stays within wa when continuing from this workspace.
+  pub fn is_consistent(&self, n: usize) -> bool {
+    let (m, isave) = (self.m, &self.isave);
+    if m == 0 || m > self.mmax || self.wa.len() != Self::wa_len(n, self.mmax).unwrap_or(0) || self.iwa.len() != 3 * n {
+      return false;
+    }
+    // setulb sets up its layout at START
+    if self.task == START as i64 {
+      return true;
+    }
+    // isave(1)..isave(16) as set by setulb: the sizes mn, m^2 and 4m^2,
+    // then the 1-based offsets of ws, wy, sy, ss, wt, wn, snd, z, r, d, t,
+    // xp and wa
+    let (mn, mm) = ((m * n) as i64, (m * m) as i64);
+    let mut expected = [0; 16];
+    expected[..4].copy_from_slice(&[mn, mm, 4 * mm, 1]);
+    let sizes = [mn, mn, mm, mm, mm, 4 * mm, 4 * mm, n as i64, n as i64, n as i64, n as i64, n as i64];
+    for (k, size) in sizes.iter().enumerate() {
+      expected[k + 4] = expected[k + 3] + size;
+    }
+    if isave[..16] != expected || expected[15] - 1 + 8 * m as i64 > self.wa.len() as i64 {
+      return false;
+    }
+    // head, col and itail of mainlb
+    let m = m as i64;
+    (1..=m).contains(&isave[26]) && (0..=m).contains(&isave[27]) && (0..=m).contains(&isave[28])
+  }
+
+  /// The number of iterations, summed over restarts.
+  pub fn niter(&self) -> usize {
+    // iter_offset is only set together with iter = 1 in isave(30)
+    (self.niter + self.isave[29].max(0) as usize).saturating_sub(self.iter_offset)
+  }
+
+  /// The number of f and g evaluations, summed over restarts.
+  pub fn nfg(&self) -> usize {
+    self.nfg + self.isave[33].max(0) as usize
+  }
+
+  /// The point x = scale * y for setulb's variables y.
+  pub fn original_x<'b>(&self, y: &'b [f64]) -> Cow<'b, [f64]> {
+    match &self.scale {
+      Some(s) => y.iter().zip(s).map(|(yi, si)| yi * si).collect(),
+      None => Cow::Borrowed(y),
+    }
+  }
+
+  /// The gradient of f with respect to x for the gradient g seen by setulb.
+  pub fn original_g<'b>(&self, g: &'b [f64]) -> Cow<'b, [f64]> {
+    let c = self.fscale;
+    match &self.scale {
+      Some(s) => g.iter().zip(s).map(|(gi, si)| gi / si / c).collect(),
+      None if c != 1.0 => g.iter().map(|gi| gi / c).collect(),
+      None => Cow::Borrowed(g),
+    }
+  }
+
+  /// f for the value seen by setulb.
+  pub fn original_f(&self, f: f64) -> f64 {
+    f / self.fscale + self.foffset
+  }
+
+  /// The value seen by setulb for f, scaling g along with it.
+  pub fn scale_objective(&self, f: f64, g: &mut [f64]) -> f64 {
+    if self.fscale != 1.0 {
+      g.iter_mut().for_each(|gi| *gi *= self.fscale);
+    }
+    (f - self.foffset) * self.fscale
+  }
+
+  /// Turn the gradient with respect to x into the gradient with respect
+  /// to y.
+  pub fn scale_gradient(&self, g: &mut [f64]) {
+    if let Some(s) = &self.scale {
+      g.iter_mut().zip(s).for_each(|(gi, si)| *gi *= si);
+    }
+  }
+
+  /// Prepare for restarting setulb from the current iterate with cleared
+  /// memory, optionally with fewer corrections `m`.
+  pub fn restart(&mut self, m: Option<usize>) {
+    self.nrestart += 1;
+    self.niter = self.niter();
+    self.nfg = self.nfg();
+    self.iter_offset = 0;
+    if let Some(m) = m {
+      // the workspace was allocated for mmax
+      self.m = m.min(self.mmax);
+    }
+    self.task = START.into();
+  }
+}
+// workspace:1 ends here
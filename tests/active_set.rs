@@ -0,0 +1,43 @@
+// [[file:../lbfgsb.note::*active_set.rs][active_set.rs:1]]
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use lbfgsb::shared::{LbfgsbParameter, LbfgsbProblem};
+
+#[test]
+fn test_active_sets() -> Result<()> {
+    // minimum at (-1, 3, 0.5, -2), beyond the bounds of x0, x1 and x3
+    let c = [-1.0, 3.0, 0.5, -2.0];
+    let evaluate = |x: &[f64], g: &mut [f64]| {
+        let mut f = 0.0;
+        for i in 0..4 {
+            g[i] = x[i] - c[i];
+            f += 0.5 * g[i] * g[i];
+        }
+        Ok(f)
+    };
+    let mut problem = LbfgsbProblem::build(vec![1.0; 4], evaluate);
+    problem.set_bounds(vec![(Some(0.0), None), (None, Some(2.0)), (None, None), (Some(0.0), Some(1.0))]);
+
+    let counts = Arc::new(Mutex::new(vec![]));
+    let c2 = counts.clone();
+    problem.observer = Some(Box::new(move |progress| {
+        c2.lock().unwrap().push((progress.nfree, progress.nactive, progress.nfreed, progress.nbound));
+        Ok(true)
+    }));
+    let result = lbfgsb::router::lbfgsb(&mut problem, &LbfgsbParameter::default())?;
+    assert_eq!(result.at_lower, vec![0, 3]);
+    assert_eq!(result.at_upper, vec![1]);
+
+    let counts = counts.lock().unwrap();
+    assert!(!counts.is_empty());
+    for &(nfree, nactive, _, _) in counts.iter() {
+        assert_eq!(nfree + nactive, 4);
+    }
+    // the variables hit their bounds in the first iteration and stay there
+    assert_eq!(counts.last().unwrap().1, 3);
+    assert!(counts.iter().all(|&(_, _, nfreed, _)| nfreed == 0));
+
+    Ok(())
+}
+// active_set.rs:1 ends here
@@ -0,0 +1,31 @@
+// [[file:../lbfgsb.note::*best.rs][best.rs:1]]
+mod common;
+
+use anyhow::Result;
+use lbfgsb::shared::{LbfgsbParameter, LbfgsbProblem};
+
+use common::evaluate;
+
+#[test]
+fn test_best_iterate() -> Result<()> {
+    let mut param = LbfgsbParameter::default();
+    let mut problem = LbfgsbProblem::build(vec![0.0], evaluate);
+    problem.set_bounds(vec![(Some(-10.0), Some(10.0))]);
+    let result = lbfgsb::router::lbfgsb(&mut problem, &param)?;
+    let best = result.best.expect("best point");
+    assert!(best.f.is_finite());
+    assert!(best.f <= problem.f);
+    assert!(best.x[0] <= 2.0);
+
+    param.return_best = true;
+    let mut problem = LbfgsbProblem::build(vec![0.0], evaluate);
+    problem.set_bounds(vec![(Some(-10.0), Some(10.0))]);
+    let result = lbfgsb::router::lbfgsb(&mut problem, &param)?;
+    let best = result.best.expect("best point");
+    assert_eq!(problem.x, best.x);
+    assert_eq!(problem.f, best.f);
+    assert_eq!(problem.g, best.g);
+
+    Ok(())
+}
+// best.rs:1 ends here
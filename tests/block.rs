@@ -0,0 +1,107 @@
+// [[file:../lbfgsb.note::*block.rs][block.rs:1]]
+use anyhow::Result;
+use lbfgsb::block::{block_coordinate, BlockCoordinate};
+use lbfgsb::shared::{LbfgsbParameter, LbfgsbProblem};
+
+// sum of (x_i - x_{i+1})^2 + sum of (x_i - i)^2, coupling neighbours
+fn evaluate(x: &[f64], g: &mut [f64]) -> Result<f64> {
+    let n = x.len();
+    let mut f = 0.0;
+    for i in 0..n {
+        let d = x[i] - i as f64;
+        f += d * d;
+        g[i] = 2.0 * d;
+    }
+    for i in 0..n - 1 {
+        let d = x[i] - x[i + 1];
+        f += d * d;
+        g[i] += 2.0 * d;
+        g[i + 1] -= 2.0 * d;
+    }
+    Ok(f)
+}
+
+#[test]
+fn test_block_coordinate() -> Result<()> {
+    let n = 10;
+    let param = LbfgsbParameter::default();
+    let mut full = LbfgsbProblem::build(vec![0.0; n], evaluate);
+    full.set_bounds((0..n).map(|_| (Some(0.0), Some(7.0))));
+    lbfgsb::router::lbfgsb(&mut full, &param)?;
+
+    let mut problem = LbfgsbProblem::build(vec![0.0; n], evaluate);
+    problem.set_bounds((0..n).map(|_| (Some(0.0), Some(7.0))));
+    let blocks = BlockCoordinate::new(vec![(0..5).collect(), (5..10).collect()]);
+    let result = block_coordinate(&mut problem, &param, &blocks)?;
+    assert!(result.converged);
+    assert!(result.ncycle > 1);
+    assert!(result.pgnorm <= 1e-5);
+    assert!(problem.select.is_none());
+    for i in 0..n {
+        assert!((problem.x[i] - full.x[i]).abs() < 1e-4);
+    }
+
+    // a variable outside all blocks keeps its value, and its gradient keeps
+    // the full projected gradient from converging
+    let mut problem = LbfgsbProblem::build(vec![0.0; n], evaluate);
+    problem.set_bounds((0..n).map(|_| (Some(0.0), Some(7.0))));
+    let mut blocks = BlockCoordinate::new(vec![(0..5).collect(), (6..10).collect()]);
+    blocks.max_cycles = 3;
+    let result = block_coordinate(&mut problem, &param, &blocks)?;
+    assert_eq!(problem.x[5], 0.0);
+    assert!(!result.converged);
+    assert_eq!(result.ncycle, 3);
+    assert_eq!(result.pgnorm, problem.kkt().variables[5].projected_gradient.abs());
+
+    // without cycles pgnorm is that of the initial x
+    let mut problem = LbfgsbProblem::build(vec![0.0; n], evaluate);
+    problem.set_bounds((0..n).map(|_| (Some(0.0), Some(7.0))));
+    blocks.max_cycles = 0;
+    let result = block_coordinate(&mut problem, &param, &blocks)?;
+    assert_eq!((result.ncycle, result.nfg), (0, 1));
+    assert!(!result.converged);
+    assert!(result.pgnorm > 0.0);
+    assert_eq!(result.pgnorm, problem.kkt().variables.iter().map(|v| v.projected_gradient.abs()).fold(0.0, f64::max));
+
+    // a block of fixed variables only is skipped
+    let mut problem = LbfgsbProblem::build(vec![0.0; n], evaluate);
+    problem.set_bounds((0..n).map(|i| if i == 5 { (Some(1.0), Some(1.0)) } else { (Some(0.0), Some(7.0)) }));
+    let param = LbfgsbParameter { eliminate_fixed: true, ..Default::default() };
+    let blocks = BlockCoordinate::new(vec![(0..5).collect(), vec![5], (6..10).collect()]);
+    assert!(block_coordinate(&mut problem, &param, &blocks)?.converged);
+    assert_eq!(problem.x[5], 1.0);
+
+    let blocks = BlockCoordinate::new(vec![vec![10]]);
+    assert!(block_coordinate(&mut problem, &param, &blocks).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_block_gradient_at_x() -> Result<()> {
+    // (x_0 - 5)^2 + (x_1 - 5)^2, undefined beyond x_0 = 2: the line search
+    // of the last block runs into NaN and leaves g of x_1 from there
+    let evaluate = |x: &[f64], g: &mut [f64]| {
+        if x[0] > 2.0 {
+            g.fill(f64::NAN);
+            return Ok(f64::NAN);
+        }
+        g[0] = 2.0 * (x[0] - 5.0);
+        g[1] = 2.0 * (x[1] - 5.0);
+        Ok((x[0] - 5.0).powi(2) + (x[1] - 5.0).powi(2))
+    };
+    let mut problem = LbfgsbProblem::build(vec![0.0; 2], evaluate);
+    problem.set_bounds(vec![(None, None); 2]);
+    let mut blocks = BlockCoordinate::new(vec![vec![1], vec![0]]);
+    blocks.max_cycles = 2;
+    let result = block_coordinate(&mut problem, &LbfgsbParameter::default(), &blocks)?;
+    // pgnorm, f and g are those of the final x
+    let mut g = vec![0.0; 2];
+    let f = evaluate(&problem.x, &mut g)?;
+    assert_eq!((problem.f, &problem.g), (f, &g));
+    assert_eq!(result.pgnorm, g[0].abs().max(g[1].abs()));
+    assert!(!result.converged);
+
+    Ok(())
+}
+// block.rs:1 ends here
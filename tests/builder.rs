@@ -0,0 +1,50 @@
+// [[file:../lbfgsb.note::*builder.rs][builder.rs:1]]
+use anyhow::Result;
+use lbfgsb::builder::ParameterBuilder;
+use lbfgsb::shared::{LbfgsbParameter, LbfgsbProblem, StopReason};
+
+#[test]
+fn test_scipy_parameters() -> Result<()> {
+    let param = LbfgsbParameter::builder().build()?;
+    assert_eq!(param.m, 10);
+    assert_eq!(param.factr, 1E7);
+    assert_eq!(param.pgtol, 1E-5);
+    assert_eq!(param.stop.max_iter, Some(15000));
+
+    let param = LbfgsbParameter::builder().ftol(1E-12).gtol(1E-8).maxcor(7).build()?;
+    assert!((param.factr - 1E-12 / f64::EPSILON).abs() < 1E-6);
+    assert_eq!((param.pgtol, param.m), (1E-8, 7));
+
+    assert!(LbfgsbParameter::builder().maxcor(0).build().is_err());
+    assert!(LbfgsbParameter::builder().ftol(-1.0).build().is_err());
+    assert!(LbfgsbParameter::builder().gtol(f64::NAN).build().is_err());
+    assert!(LbfgsbParameter::builder().maxls(0).build().is_err());
+
+    let quick = ParameterBuilder::quick().build()?;
+    let accurate = ParameterBuilder::high_accuracy().build()?;
+    assert!(quick.factr > accurate.factr && quick.pgtol > accurate.pgtol);
+
+    // maxiter stops the run as in scipy
+    let evaluate = |x: &[f64], g: &mut [f64]| {
+        let (a, b) = (1.0 - x[0], x[1] - x[0] * x[0]);
+        g[0] = -2.0 * a - 400.0 * x[0] * b;
+        g[1] = 200.0 * b;
+        Ok(a * a + 100.0 * b * b)
+    };
+    let mut problem = LbfgsbProblem::build(vec![-1.2, 1.0], evaluate);
+    problem.set_bounds(vec![(None, None); 2]);
+    let param = LbfgsbParameter::builder().maxiter(5).build()?;
+    let result = lbfgsb::router::lbfgsb(&mut problem, &param)?;
+    assert_eq!(result.stop, Some(StopReason::MaxIter));
+    assert_eq!(result.niter, 5);
+
+    let mut problem = LbfgsbProblem::build(vec![-1.2, 1.0], evaluate);
+    problem.set_bounds(vec![(None, None); 2]);
+    let param = LbfgsbParameter::builder().maxfun(10).build()?;
+    let result = lbfgsb::router::lbfgsb(&mut problem, &param)?;
+    assert_eq!(result.stop, Some(StopReason::MaxFun));
+    assert!(result.nfg > 10);
+
+    Ok(())
+}
+// builder.rs:1 ends here
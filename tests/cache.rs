@@ -0,0 +1,35 @@
+// [[file:../lbfgsb.note::*cache.rs][cache.rs:1]]
+use anyhow::Result;
+use lbfgsb::shared::{LbfgsbParameter, LbfgsbProblem};
+
+#[test]
+fn test_eval_cache() -> Result<()> {
+    let mut ncall = 0;
+    let evaluate = |x: &[f64], g: &mut [f64]| {
+        ncall += 1;
+        g[0] = 2.0 * (x[0] - 1.0);
+        g[1] = 20.0 * (x[1] + 1.0);
+        Ok((x[0] - 1.0).powi(2) + 10.0 * (x[1] + 1.0).powi(2))
+    };
+    let mut problem = LbfgsbProblem::build(vec![3.0, 3.0], evaluate);
+    problem.set_bounds(vec![(Some(0.0), Some(5.0)), (None, Some(0.0))]);
+    let param = LbfgsbParameter { cache_size: 4, ..Default::default() };
+    let result = lbfgsb::router::lbfgsb(&mut problem, &param)?;
+    drop(problem);
+
+    let stats = result.cache.expect("cache stats");
+    assert_eq!(stats.hits + stats.misses, result.nfg);
+    assert_eq!(stats.misses, ncall);
+
+    // no statistics without caching
+    let mut problem = LbfgsbProblem::build(vec![3.0], |x: &[f64], g: &mut [f64]| {
+        g[0] = 2.0 * x[0];
+        Ok(x[0] * x[0])
+    });
+    problem.set_bounds(vec![(None, None)]);
+    let result = lbfgsb::router::lbfgsb(&mut problem, &LbfgsbParameter::default())?;
+    assert!(result.cache.is_none());
+
+    Ok(())
+}
+// cache.rs:1 ends here
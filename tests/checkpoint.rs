@@ -0,0 +1,212 @@
+// [[file:../lbfgsb.note::*checkpoint.rs][checkpoint.rs:1]]
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::Result;
+use lbfgsb::checkpoint::Checkpoint;
+use lbfgsb::scaling::ObjectiveScaling;
+use lbfgsb::shared::{LbfgsbParameter, LbfgsbProblem, StopReason};
+use lbfgsb::stopping::StopCriteria;
+
+// The extended Rosenbrock function of driver1
+fn evaluate(x: &[f64], g: &mut [f64]) -> Result<f64> {
+    let n = x.len();
+    let mut d1 = x[0] - 1.0;
+    let mut f = d1 * d1 * 0.25;
+    for i in 1..n {
+        let d2 = x[i - 1] * x[i - 1];
+        d1 = x[i] - d2;
+        f += d1 * d1;
+    }
+    f *= 4.0;
+
+    let mut d1 = x[1] - x[0] * x[0];
+    g[0] = (x[0] - 1.0) * 2.0 - x[0] * 16.0 * d1;
+    for i in 1..n - 1 {
+        let t1 = d1;
+        d1 = x[i + 1] - x[i] * x[i];
+        g[i] = t1 * 8.0 - x[i] * 16.0 * d1;
+    }
+    g[n - 1] = d1 * 8.0;
+
+    Ok(f)
+}
+
+type EvalFn = fn(&[f64], &mut [f64]) -> Result<f64>;
+
+fn build() -> LbfgsbProblem<EvalFn> {
+    let n = 25;
+    let mut problem = LbfgsbProblem::build(vec![3.0; n], evaluate as EvalFn);
+    let bounds = (0..n).map(|i| if i % 2 == 0 { (Some(1.0), Some(100.0)) } else { (Some(-100.0), Some(100.0)) });
+    problem.set_bounds(bounds);
+    problem
+}
+
+fn bits(v: &[f64]) -> Vec<u64> {
+    v.iter().map(|vi| vi.to_bits()).collect()
+}
+
+#[test]
+fn test_checkpoint_resume() -> Result<()> {
+    let param = LbfgsbParameter { factr: 1E7, ..Default::default() };
+    let mut problem = build();
+    let full = lbfgsb::router::lbfgsb(&mut problem, &param)?;
+    assert!(full.niter > 10);
+
+    // stop after 10 iterations, keeping a checkpoint
+    let saved = Arc::new(Mutex::new(Vec::new()));
+    let mut interrupted = build();
+    let s = saved.clone();
+    interrupted.observer = Some(Box::new(move |progress| {
+        if progress.niter < 10 {
+            return Ok(true);
+        }
+        *s.lock().unwrap() = progress.checkpoint().to_bytes();
+        Ok(false)
+    }));
+    let first = lbfgsb::router::lbfgsb(&mut interrupted, &param)?;
+    assert_eq!(first.stop, Some(StopReason::Observer));
+
+    // continue from the serialized state in a fresh problem
+    let checkpoint = Checkpoint::from_bytes(&saved.lock().unwrap())?;
+    assert_eq!(checkpoint.niter(), 10);
+    let mut resumed = build();
+    resumed.x = vec![0.0; 25];
+    resumed.resume = Some(checkpoint);
+    let last = lbfgsb::router::lbfgsb(&mut resumed, &param)?;
+
+    assert_eq!(last.task, full.task);
+    assert_eq!(last.niter, full.niter);
+    assert_eq!(last.nfg, full.nfg);
+    assert_eq!(resumed.f.to_bits(), problem.f.to_bits());
+    assert_eq!(bits(&resumed.x), bits(&problem.x));
+    assert_eq!(bits(&resumed.g), bits(&problem.g));
+
+    // corrupted data is rejected
+    let mut bytes = saved.lock().unwrap().clone();
+    assert!(Checkpoint::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    bytes[8] = 99;
+    assert!(Checkpoint::from_bytes(&bytes).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_checkpoint_stop_history() -> Result<()> {
+    let stagnation = StopCriteria { stagnation_tol: Some(1e-3), stagnation_iters: 5, ..Default::default() };
+    let x_change = StopCriteria { x_tol: Some(1e-2), x_iters: 3, ..Default::default() };
+    for (stop, reason) in [(stagnation, StopReason::Stagnation), (x_change, StopReason::XChange)] {
+        let param = LbfgsbParameter { factr: 0.0, pgtol: 0.0, stop, ..Default::default() };
+        let mut problem = build();
+        let full = lbfgsb::router::lbfgsb(&mut problem, &param)?;
+        assert_eq!(full.stop, Some(reason));
+
+        // interrupt within the window of the criterion
+        let saved = Arc::new(Mutex::new(Vec::new()));
+        let mut interrupted = build();
+        let s = saved.clone();
+        let at = full.niter - 2;
+        interrupted.observer = Some(Box::new(move |progress| {
+            if progress.niter < at {
+                return Ok(true);
+            }
+            *s.lock().unwrap() = progress.checkpoint().to_bytes();
+            Ok(false)
+        }));
+        lbfgsb::router::lbfgsb(&mut interrupted, &param)?;
+
+        let mut resumed = build();
+        resumed.resume = Some(Checkpoint::from_bytes(&saved.lock().unwrap())?);
+        let last = lbfgsb::router::lbfgsb(&mut resumed, &param)?;
+        assert_eq!(last.stop, full.stop);
+        assert_eq!(last.niter, full.niter);
+        assert_eq!(bits(&resumed.x), bits(&problem.x));
+    }
+
+    Ok(())
+}
+
+// The bytes with the little-endian u64 or i64 at `at` replaced.
+fn patched(bytes: &[u8], at: usize, v: u64) -> Vec<u8> {
+    let mut bytes = bytes.to_vec();
+    bytes[at..at + 8].copy_from_slice(&v.to_le_bytes());
+    bytes
+}
+
+// The offset of the only occurrence of `values` in `bytes`.
+fn find(bytes: &[u8], values: &[u64]) -> usize {
+    let pattern: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+    let mut at = bytes.windows(pattern.len()).enumerate().filter(|(_, w)| *w == pattern).map(|(k, _)| k);
+    let first = at.next().expect("pattern not found");
+    assert!(at.next().is_none(), "pattern not unique");
+    first
+}
+
+// The offset just past the length-prefixed array at `at`.
+fn skip(bytes: &[u8], at: usize) -> usize {
+    at + 8 + 8 * u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap()) as usize
+}
+
+#[test]
+fn test_checkpoint_untrusted() -> Result<()> {
+    // factr and the objective offset are set to values that occur once in
+    // the serialized checkpoint, to locate the fields around them
+    let (factr, offset) = (12345.625, 678.875);
+    let param = LbfgsbParameter { factr, ..Default::default() };
+    let saved = Arc::new(Mutex::new(Vec::new()));
+    let mut problem = build();
+    problem.objective_scaling = Some(ObjectiveScaling::Fixed { factor: 1.0, offset });
+    let s = saved.clone();
+    problem.observer = Some(Box::new(move |progress| {
+        *s.lock().unwrap() = progress.checkpoint().to_bytes();
+        Ok(progress.niter < 5)
+    }));
+    lbfgsb::router::lbfgsb(&mut problem, &param)?;
+    let bytes = saved.lock().unwrap().clone();
+    assert!(Checkpoint::from_bytes(&bytes).is_ok());
+
+    // every truncation is rejected
+    for k in 0..bytes.len() {
+        assert!(Checkpoint::from_bytes(&bytes[..k]).is_err());
+    }
+
+    // mmax precedes factr
+    let mmax = find(&bytes, &[factr.to_bits()]) - 8;
+    assert_eq!(bytes[mmax..][..8], 5u64.to_le_bytes());
+    for v in [u64::MAX, 1 << 32, 1 << 20] {
+        assert!(Checkpoint::from_bytes(&patched(&bytes, mmax, v)).is_err());
+    }
+
+    // isave starts with mn, m^2, 4m^2 and the offset of ws in wa
+    let (n, m) = (25, 5);
+    let isave = find(&bytes, &[n * m, m * m, 4 * m * m, 1]);
+    for (k, v) in [(3, 1 << 40), (3, 2), (26, 0), (26, 100), (27, 100), (27, -1i64 as u64)] {
+        assert!(Checkpoint::from_bytes(&patched(&bytes, isave + 8 * k, v)).is_err());
+    }
+    // wa follows foffset and task, and is as long as the setulb layout asks
+    let wa = find(&bytes, &[offset.to_bits()]) + 16;
+    for v in [0, 1 << 20, u64::MAX] {
+        assert!(Checkpoint::from_bytes(&patched(&bytes, wa, v)).is_err());
+    }
+
+    // iter_offset follows isave, lsave, nrestart, niter and nfg
+    let iter_offset = skip(&bytes, skip(&bytes, isave - 8)) + 24;
+    assert_eq!(bytes[iter_offset..][..8], 0u64.to_le_bytes());
+    assert!(Checkpoint::from_bytes(&patched(&bytes, iter_offset, 2)).is_err());
+    // a warm start offset with iter = 0 in isave(30) counts no iterations
+    let warm = patched(&patched(&bytes, iter_offset, 1), isave + 8 * 29, 0);
+    assert_eq!(Checkpoint::from_bytes(&warm)?.niter(), 0);
+
+    // the setulb timers in dsave(7..9), just before isave, need not be valid
+    // durations
+    let dsave = isave - 8 - 8 * 29;
+    for v in [f64::INFINITY, f64::NAN, 1e300] {
+        let mut resumed = build();
+        resumed.resume = Some(Checkpoint::from_bytes(&patched(&bytes, dsave + 8 * 6, v.to_bits()))?);
+        let result = lbfgsb::router::lbfgsb(&mut resumed, &param)?;
+        assert_eq!(result.timing.cauchy, Duration::ZERO);
+    }
+
+    Ok(())
+}
+// checkpoint.rs:1 ends here
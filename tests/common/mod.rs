@@ -0,0 +1,13 @@
+// [[file:../../lbfgsb.note::*common][common:1]]
+use anyhow::Result;
+
+/// (x - 5)^2, undefined beyond x = 2: f and g are NaN there.
+pub fn evaluate(x: &[f64], g: &mut [f64]) -> Result<f64> {
+    if x[0] > 2.0 {
+        g[0] = f64::NAN;
+        return Ok(f64::NAN);
+    }
+    g[0] = 2.0 * (x[0] - 5.0);
+    Ok((x[0] - 5.0).powi(2))
+}
+// common:1 ends here
@@ -1,6 +1,6 @@
 // [[file:~/Workspace/Programming/rust-libs/l-bfgs-b-c/lbfgsb.note::*driver1.rs][driver1.rs:1]]
 use anyhow::Result;
-use lbfgsb::*;
+use lbfgsb::shared::{LbfgsbParameter, LbfgsbProblem};
 use vecfx::*; // for calculate gradient norm
 
 /// Compute function value f for the sample problem.
@@ -67,13 +67,16 @@ fn test_lbfgs() -> Result<()> {
     }
     println!("     Solving sample problem (Rosenbrock test fcn).");
     println!("      (f = 0.0 at the optimal solution.)");
-    let bounds: Vec<_> = l.into_iter().zip(u.into_iter()).collect();
-    let result = lbfgsb::router::lbfgsb(x, &bounds, evaluate, 5, 1e1, 1e-5, -1)?;
+    let bounds = l.into_iter().zip(u).map(|(l, u)| (Some(l), Some(u)));
+    let mut problem = LbfgsbProblem::build(x, evaluate);
+    problem.set_bounds(bounds);
+    let param = LbfgsbParameter::default();
+    lbfgsb::router::lbfgsb(&mut problem, &param)?;
     println!("     ... Finished sample problem (Rosenbrock test fcn).");
-    println!("     ... The optimal value of f is: {:?}", result);
+    println!("     ... The optimal value of f is: {:?}", problem.f);
     // assert!(opt.fx() <= 1e-8);
     // assert!(dbg!(opt.gx().vec2norm()) < 1e-3);
-    assert!(dbg!(result.vec2norm()) == 6.541532444922342);
+    assert!(dbg!(problem.x.vec2norm()) == 6.541532444922342);
 
     Ok(())
 }
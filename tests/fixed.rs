@@ -0,0 +1,58 @@
+// [[file:../lbfgsb.note::*fixed.rs][fixed.rs:1]]
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use lbfgsb::shared::{LbfgsbParameter, LbfgsbProblem};
+
+// sum of (x_i - i)^2 + (x_0 x_1 - 1)^2, with all but x_0 and x_1 fixed
+fn solve(eliminate_fixed: bool) -> Result<(Vec<f64>, Vec<f64>, usize, usize)> {
+    let n = 20;
+    let evaluate = |x: &[f64], g: &mut [f64]| {
+        assert_eq!(x.len(), 20);
+        let mut f = 0.0;
+        for i in 0..x.len() {
+            let d = x[i] - i as f64;
+            f += d * d;
+            g[i] = 2.0 * d;
+        }
+        let r = x[0] * x[1] - 1.0;
+        f += r * r;
+        g[0] += 2.0 * r * x[1];
+        g[1] += 2.0 * r * x[0];
+        Ok(f)
+    };
+    let mut problem = LbfgsbProblem::build(vec![0.5; n], evaluate);
+    problem.set_bounds((0..n).map(|i| if i < 2 { (None, None) } else { (Some(0.5 * i as f64), Some(0.5 * i as f64)) }));
+    let niter = Arc::new(Mutex::new(0));
+    let seen = niter.clone();
+    problem.observer = Some(Box::new(move |progress| {
+        assert_eq!(progress.x.len(), 20);
+        assert_eq!(progress.x[5], 2.5);
+        *seen.lock().unwrap() += 1;
+        Ok(true)
+    }));
+    let param = LbfgsbParameter { eliminate_fixed, ..Default::default() };
+    let result = lbfgsb::router::lbfgsb(&mut problem, &param)?;
+    assert!(problem.observer.is_some());
+    assert_eq!(result.memory.s[0].len(), 20);
+    let nobserved = *niter.lock().unwrap();
+    Ok((problem.x, problem.g, result.niter, nobserved))
+}
+
+#[test]
+fn test_eliminate_fixed() -> Result<()> {
+    let (x0, g0, niter0, _) = solve(false)?;
+    let (x1, g1, niter1, nobserved) = solve(true)?;
+    assert_eq!(niter0, niter1);
+    assert_eq!(nobserved, niter1);
+    for i in 0..20 {
+        assert!((x0[i] - x1[i]).abs() < 1e-8);
+        assert!((g0[i] - g1[i]).abs() < 1e-6);
+    }
+    // the gradient of the fixed variables at the solution
+    assert_eq!(x1[5], 2.5);
+    assert_eq!(g1[5], -5.0);
+
+    Ok(())
+}
+// fixed.rs:1 ends here
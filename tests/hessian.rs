@@ -0,0 +1,56 @@
+// [[file:../lbfgsb.note::*hessian.rs][hessian.rs:1]]
+use anyhow::Result;
+use lbfgsb::shared::{LbfgsbParameter, LbfgsbProblem};
+
+#[test]
+fn test_inverse_hessian() -> Result<()> {
+    let n = 4;
+    // a quadratic with Hessian diag(1, 2, 5, 10)
+    let w = [1.0, 2.0, 5.0, 10.0];
+    let evaluate = |x: &[f64], g: &mut [f64]| {
+        let mut f = 0.0;
+        for i in 0..n {
+            f += 0.5 * w[i] * (x[i] - 1.0).powi(2);
+            g[i] = w[i] * (x[i] - 1.0);
+        }
+        Ok(f)
+    };
+    let mut problem = LbfgsbProblem::build(vec![0.0; n], evaluate);
+    problem.set_bounds(vec![(None, None); n]);
+    let param = LbfgsbParameter { m: 10, pgtol: 1e-10, ..Default::default() };
+    let result = lbfgsb::router::lbfgsb(&mut problem, &param)?;
+    assert!(!result.memory.is_empty());
+
+    let hinv = result.memory.inverse_hessian(n);
+    let dense = hinv.to_dense();
+    for (i, row) in dense.iter().enumerate() {
+        for (j, hij) in row.iter().enumerate() {
+            assert!((hij - dense[j][i]).abs() < 1e-10);
+        }
+        assert!(row[i] > 0.0);
+    }
+
+    // the secant condition H y = s holds for the latest pair
+    let (s, y) = (result.memory.s.last().unwrap(), result.memory.y.last().unwrap());
+    let hy = hinv.apply(y)?;
+    for i in 0..n {
+        assert!((hy[i] - s[i]).abs() < 1e-8 * s[i].abs().max(1.0));
+    }
+
+    // the dense matrix agrees with the operator
+    let v = [1.0, -2.0, 0.5, 3.0];
+    let hv = hinv.apply(&v)?;
+    for (row, hvi) in dense.iter().zip(&hv) {
+        let di: f64 = row.iter().zip(&v).map(|(hij, vj)| hij * vj).sum();
+        assert!((di - hvi).abs() < 1e-12);
+    }
+
+    // without pairs the operator is I/theta
+    let empty = lbfgsb::memory::CorrectionPairs { theta: 2.0, ..Default::default() };
+    assert_eq!(empty.inverse_hessian(2).apply(&[1.0, 4.0])?, vec![0.5, 2.0]);
+    // a vector of the wrong length is an error
+    assert!(empty.inverse_hessian(2).apply(&[1.0]).is_err());
+
+    Ok(())
+}
+// hessian.rs:1 ends here
@@ -0,0 +1,42 @@
+// [[file:../lbfgsb.note::*kkt.rs][kkt.rs:1]]
+use anyhow::Result;
+use lbfgsb::kkt::{BoundStatus, KktReport};
+use lbfgsb::shared::{LbfgsbParameter, LbfgsbProblem};
+
+#[test]
+fn test_kkt_report() -> Result<()> {
+    // minimum at (-1, 3, 0.5), with x0 >= 0 and x1 <= 2 active
+    let c = [-1.0, 3.0, 0.5];
+    let evaluate = |x: &[f64], g: &mut [f64]| {
+        let mut f = 0.0;
+        for i in 0..3 {
+            g[i] = x[i] - c[i];
+            f += 0.5 * g[i] * g[i];
+        }
+        Ok(f)
+    };
+    let mut problem = LbfgsbProblem::build(vec![1.0; 3], evaluate);
+    problem.set_bounds(vec![(Some(0.0), None), (None, Some(2.0)), (Some(-5.0), Some(5.0))]);
+    let param = LbfgsbParameter { pgtol: 1e-10, ..Default::default() };
+    lbfgsb::router::lbfgsb(&mut problem, &param)?;
+
+    let report = problem.kkt();
+    let status: Vec<_> = report.variables.iter().map(|v| v.status).collect();
+    assert_eq!(status, vec![BoundStatus::Lower, BoundStatus::Upper, BoundStatus::Free]);
+    assert!((report.variables[0].multiplier - 1.0).abs() < 1e-8);
+    assert!((report.variables[1].multiplier - 1.0).abs() < 1e-8);
+    assert!(report.residual < 1e-8);
+
+    // a point at its lower bound with a negative multiplier is not optimal
+    let report = KktReport::new(&[0.0], &[-2.0], &[0.0], &[0.0], &[1]);
+    assert_eq!(report.variables[0].status, BoundStatus::Lower);
+    assert_eq!(report.variables[0].multiplier, -2.0);
+    assert_eq!(report.residual, 2.0);
+
+    // and neither is an infeasible one
+    let report = KktReport::new(&[3.5], &[0.0], &[0.0], &[3.0], &[2]);
+    assert_eq!(report.residual, 0.5);
+
+    Ok(())
+}
+// kkt.rs:1 ends here
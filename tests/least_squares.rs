@@ -0,0 +1,86 @@
+// [[file:../lbfgsb.note::*least_squares.rs][least_squares.rs:1]]
+use anyhow::Result;
+use lbfgsb::least_squares::{LeastSquares, Loss};
+use lbfgsb::shared::LbfgsbParameter;
+
+// y = 3 exp(-0.5 t) at t = 0, 0.5, ..., 9.5, with two gross outliers
+fn data() -> (Vec<f64>, Vec<f64>) {
+    let t: Vec<f64> = (0..20).map(|i| 0.5 * i as f64).collect();
+    let mut y: Vec<f64> = t.iter().map(|ti| 3.0 * (-0.5 * ti).exp()).collect();
+    y[3] += 2.0;
+    y[12] -= 1.5;
+    (t, y)
+}
+
+fn residuals(t: Vec<f64>, y: Vec<f64>) -> impl FnMut(&[f64], &mut [f64]) -> Result<()> {
+    move |x: &[f64], r: &mut [f64]| {
+        for i in 0..t.len() {
+            r[i] = x[0] * (-x[1] * t[i]).exp() - y[i];
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_least_squares() -> Result<()> {
+    let (t, y) = data();
+    let param = LbfgsbParameter { pgtol: 1e-10, ..Default::default() };
+    let bounds = vec![(Some(0.0), None), (Some(0.0), Some(2.0))];
+
+    // forward differences and the analytic Jacobian agree
+    let fd = LeastSquares::new(20, residuals(t.clone(), y.clone())).solve(vec![1.0, 1.0], bounds.clone(), &param)?;
+    // the Jacobian may borrow the data
+    let jacobian = |x: &[f64], jac: &mut [f64]| {
+        for (i, ti) in t.iter().enumerate() {
+            let e = (-x[1] * ti).exp();
+            jac[2 * i] = e;
+            jac[2 * i + 1] = -x[0] * ti * e;
+        }
+        Ok(())
+    };
+    let exact = LeastSquares::new(20, residuals(t.clone(), y.clone())).jacobian(jacobian).solve(vec![1.0, 1.0], bounds.clone(), &param)?;
+    assert!((fd.x[0] - exact.x[0]).abs() < 1e-5);
+    assert!((fd.x[1] - exact.x[1]).abs() < 1e-5);
+    // cost = 1/2 |r|^2 of the reported residuals
+    let cost = 0.5 * exact.residuals.iter().map(|r| r * r).sum::<f64>();
+    assert!((exact.cost - cost).abs() < 1e-12);
+    assert!(exact.gradient.iter().all(|g| g.abs() < 1e-6));
+
+    // robust losses see through the outliers
+    for loss in [Loss::Huber, Loss::SoftL1, Loss::Cauchy] {
+        let robust = LeastSquares::new(20, residuals(t.clone(), y.clone())).loss(loss, 0.1).solve(vec![1.0, 1.0], bounds.clone(), &param)?;
+        assert!((robust.x[0] - 3.0).abs() < (exact.x[0] - 3.0).abs());
+        assert!((robust.x[0] - 3.0).abs() < 0.05, "{loss:?}: {:?}", robust.x);
+        assert!((robust.x[1] - 0.5).abs() < 0.02, "{loss:?}: {:?}", robust.x);
+    }
+
+    let invalid = LeastSquares::new(20, residuals(t.clone(), y.clone())).loss(Loss::Huber, 0.0);
+    assert!(invalid.solve(vec![1.0, 1.0], bounds.clone(), &param).is_err());
+    // one pair of bounds per variable
+    for k in [1, 3] {
+        let bounds = vec![(Some(0.0), None); k];
+        assert!(LeastSquares::new(20, residuals(t.clone(), y.clone())).solve(vec![1.0, 1.0], bounds, &param).is_err());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_least_squares_upper_bound() -> Result<()> {
+    // r = sqrt(1 - x) + 2 is undefined beyond the upper bound x = 1, so the
+    // difference steps at the solution x = 1 must go backward
+    let residuals = |x: &[f64], r: &mut [f64]| {
+        anyhow::ensure!(x[0] <= 1.0, "x = {} out of domain", x[0]);
+        r[0] = (1.0 - x[0]).sqrt() + 2.0;
+        Ok(())
+    };
+    let param = LbfgsbParameter::default();
+    for x0 in [1.0, 0.5] {
+        let sol = LeastSquares::new(1, residuals).solve(vec![x0], vec![(None, Some(1.0))], &param)?;
+        assert_eq!(sol.x, vec![1.0]);
+        assert!(sol.gradient[0] < 0.0);
+    }
+
+    Ok(())
+}
+// least_squares.rs:1 ends here
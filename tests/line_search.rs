@@ -0,0 +1,41 @@
+// [[file:../lbfgsb.note::*line_search.rs][line_search.rs:1]]
+use anyhow::Result;
+use lbfgsb::shared::task::ABNORMAL;
+use lbfgsb::shared::{LbfgsbParameter, LbfgsbProblem, LbfgsbResult};
+
+fn rosenbrock(param: &LbfgsbParameter) -> Result<LbfgsbResult> {
+    let evaluate = |x: &[f64], g: &mut [f64]| {
+        let (a, b) = (1.0 - x[0], x[1] - x[0] * x[0]);
+        g[0] = -2.0 * a - 400.0 * x[0] * b;
+        g[1] = 200.0 * b;
+        Ok(a * a + 100.0 * b * b)
+    };
+    let mut problem = LbfgsbProblem::build(vec![-1.2, 1.0], evaluate);
+    problem.set_bounds(vec![(None, None); 2]);
+    lbfgsb::router::lbfgsb(&mut problem, param)
+}
+
+#[test]
+fn test_line_search_parameters() -> Result<()> {
+    let default = rosenbrock(&LbfgsbParameter::default())?;
+
+    // a stricter curvature condition takes more evaluations per iteration
+    let strict = rosenbrock(&LbfgsbParameter { gtol: 0.01, ..Default::default() })?;
+    assert!(strict.nfg as f64 / strict.niter as f64 > default.nfg as f64 / default.niter as f64);
+
+    // an impossible curvature condition with one step per line search
+    let param = LbfgsbParameter { gtol: 1e-12, maxls: 1, ..Default::default() };
+    let failed = rosenbrock(&param)?;
+    assert_eq!(failed.task, ABNORMAL as i64);
+
+    // the parameters do not leak into later runs: the router uses its 64
+    // instances in turn, so one of the next 64 runs is on the instance of
+    // the failed one
+    for _ in 0..64 {
+        let again = rosenbrock(&LbfgsbParameter::default())?;
+        assert_eq!((again.niter, again.nfg), (default.niter, default.nfg));
+    }
+
+    Ok(())
+}
+// line_search.rs:1 ends here
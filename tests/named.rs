@@ -0,0 +1,45 @@
+// [[file:../lbfgsb.note::*named.rs][named.rs:1]]
+use anyhow::Result;
+use lbfgsb::kkt::BoundStatus;
+use lbfgsb::named::ParameterSet;
+use lbfgsb::shared::{LbfgsbParameter, LbfgsbProblem};
+
+#[test]
+fn test_named_parameters() -> Result<()> {
+    let mut params = ParameterSet::new();
+    params.add("amplitude", 1.0, Some(0.0), None)?;
+    params.add("decay", 0.5, Some(0.0), Some(0.2))?;
+    params.add("offset", 0.0, None, None)?;
+    params.add("fixed", 3.0, Some(3.0), Some(3.0))?;
+    assert!(params.add("decay", 0.0, None, None).is_err());
+    assert_eq!(params.len(), 4);
+
+    let (a, k, c) = (params.index("amplitude").unwrap(), params.index("decay").unwrap(), params.index("offset").unwrap());
+    // (a - 2)^2 + (k - 0.3)^2 + (c + 1)^2: decay ends at its upper bound
+    let evaluate = move |x: &[f64], g: &mut [f64]| {
+        g.fill(0.0);
+        g[a] = 2.0 * (x[a] - 2.0);
+        g[k] = 2.0 * (x[k] - 0.3);
+        g[c] = 2.0 * (x[c] + 1.0);
+        Ok((x[a] - 2.0).powi(2) + (x[k] - 0.3).powi(2) + (x[c] + 1.0).powi(2))
+    };
+    let (solution, _) = params.minimize(evaluate, &LbfgsbParameter::default())?;
+
+    assert!((solution.value("amplitude").unwrap() - 2.0).abs() < 1e-6);
+    assert_eq!(solution.value("decay"), Some(0.2));
+    assert!((solution.value("offset").unwrap() + 1.0).abs() < 1e-6);
+    assert_eq!(solution.value("missing"), None);
+    assert!((solution.f - 0.01).abs() < 1e-10);
+
+    assert_eq!(solution.status["amplitude"], BoundStatus::Free);
+    assert_eq!(solution.status["decay"], BoundStatus::Upper);
+    assert_eq!(solution.status["offset"], BoundStatus::Free);
+    assert_eq!(solution.status["fixed"], BoundStatus::Fixed);
+
+    // a problem over a different number of variables has no solution by name
+    let other = LbfgsbProblem::build(vec![0.0; 3], evaluate);
+    assert!(params.solution(&other).is_err());
+
+    Ok(())
+}
+// named.rs:1 ends here
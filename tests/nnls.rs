@@ -0,0 +1,51 @@
+// [[file:../lbfgsb.note::*nnls.rs][nnls.rs:1]]
+use anyhow::Result;
+use lbfgsb::nnls::nnls;
+use lbfgsb::operator::{DenseMatrix, FnOperator};
+use lbfgsb::shared::LbfgsbParameter;
+
+#[test]
+fn test_nnls() -> Result<()> {
+    let param = LbfgsbParameter { pgtol: 1e-12, ..Default::default() };
+    // the examples of scipy.optimize.nnls
+    let a = DenseMatrix::from_rows(&[vec![1.0, 0.0], vec![1.0, 0.0], vec![0.0, 1.0]])?;
+    let sol = nnls(&a, &[2.0, 1.0, 1.0], &param)?;
+    assert!((sol.x[0] - 1.5).abs() < 1e-8 && (sol.x[1] - 1.0).abs() < 1e-8);
+    assert!((sol.rnorm - 0.5f64.sqrt()).abs() < 1e-8);
+
+    let sol = nnls(&a, &[-1.0, -1.0, -1.0], &param)?;
+    assert_eq!(sol.x, vec![0.0, 0.0]);
+    assert!((sol.rnorm - 3f64.sqrt()).abs() < 1e-12);
+
+    // Lawson and Hanson: the unconstrained solution (-1, 2) has x_0 < 0,
+    // the NNLS solution is x = (0, b'a_1 / |a_1|^2)
+    let a = DenseMatrix::from_rows(&[vec![1.0, 1.0], vec![1.0, 2.0], vec![1.0, 3.0]])?;
+    let b = [1.0, 3.0, 5.0];
+    let sol = nnls(&a, &b, &param)?;
+    assert_eq!(sol.x[0], 0.0);
+    assert!((sol.x[1] - 22.0 / 14.0).abs() < 1e-8);
+
+    // the same through a matvec pair
+    let op = FnOperator::new(
+        3,
+        2,
+        |x: &[f64], y: &mut [f64]| {
+            for (i, yi) in y.iter_mut().enumerate() {
+                *yi = x[0] + (i + 1) as f64 * x[1];
+            }
+        },
+        |y: &[f64], x: &mut [f64]| {
+            x[0] = y.iter().sum();
+            x[1] = y.iter().enumerate().map(|(i, yi)| (i + 1) as f64 * yi).sum();
+        },
+    );
+    let sol2 = nnls(&op, &b, &param)?;
+    assert_eq!(sol.x, sol2.x);
+
+    assert!(nnls(&a, &[1.0], &param).is_err());
+    assert!(DenseMatrix::new(2, 2, vec![1.0; 3]).is_err());
+    assert!(DenseMatrix::from_rows(&[vec![1.0, 2.0], vec![3.0]]).is_err());
+
+    Ok(())
+}
+// nnls.rs:1 ends here
@@ -0,0 +1,43 @@
+// [[file:../lbfgsb.note::*nonfinite.rs][nonfinite.rs:1]]
+mod common;
+
+use anyhow::Result;
+use lbfgsb::nonfinite::{NonFiniteError, NonFinitePolicy};
+use lbfgsb::shared::{LbfgsbParameter, LbfgsbProblem, StopReason};
+
+use common::evaluate;
+
+type Evaluate = fn(&[f64], &mut [f64]) -> Result<f64>;
+
+fn problem() -> LbfgsbProblem<Evaluate> {
+    let mut problem = LbfgsbProblem::build(vec![0.0], evaluate as Evaluate);
+    problem.set_bounds(vec![(None, None)]);
+    problem
+}
+
+#[test]
+fn test_non_finite_policies() -> Result<()> {
+    let mut param = LbfgsbParameter { non_finite: NonFinitePolicy::Abort, ..Default::default() };
+    let err = lbfgsb::router::lbfgsb(&mut problem(), &param).unwrap_err();
+    let err = err.downcast_ref::<NonFiniteError>().expect("typed error");
+    assert!(err.x[0] > 2.0);
+    assert!(err.f.is_nan());
+
+    param.non_finite = NonFinitePolicy::ReturnBest;
+    let mut p = problem();
+    let result = lbfgsb::router::lbfgsb(&mut p, &param)?;
+    assert_eq!(result.stop, Some(StopReason::NonFinite));
+    assert_eq!(result.non_finite, 1);
+    assert_eq!(p.x, vec![1.0]);
+    assert_eq!(p.f, 16.0);
+
+    param.non_finite = NonFinitePolicy::Backtrack;
+    let mut p = problem();
+    let result = lbfgsb::router::lbfgsb(&mut p, &param)?;
+    assert!(result.non_finite > 0);
+    assert!(p.f.is_finite());
+    assert!(p.x[0] > 1.5 && p.x[0] <= 2.0);
+
+    Ok(())
+}
+// nonfinite.rs:1 ends here
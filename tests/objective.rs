@@ -0,0 +1,63 @@
+// [[file:../lbfgsb.note::*objective.rs][objective.rs:1]]
+use anyhow::Result;
+use lbfgsb::objective::{check_gradient, Objective};
+use lbfgsb::shared::{LbfgsbParameter, LbfgsbProblem};
+
+/// Shifted quadratic f(x) = sum (x_i - c_i)^2, counting its evaluations.
+struct Quadratic {
+    center: Vec<f64>,
+    neval: usize,
+}
+
+impl Objective for Quadratic {
+    type Aux = usize;
+
+    fn evaluate(&mut self, x: &[f64], g: &mut [f64]) -> Result<f64> {
+        self.neval += 1;
+        let mut f = 0.0;
+        for i in 0..x.len() {
+            let d = x[i] - self.center[i];
+            f += d * d;
+            g[i] = 2.0 * d;
+        }
+        Ok(f)
+    }
+
+    fn aux(&mut self) -> Option<usize> {
+        Some(self.neval)
+    }
+}
+
+#[test]
+fn test_struct_objective() -> Result<()> {
+    let center = vec![1.0, -2.0, 3.0];
+    let objective = Quadratic { center: center.clone(), neval: 0 };
+    let mut problem = LbfgsbProblem::build(vec![0.0; 3], objective);
+    problem.set_bounds(vec![(None, None); 3]);
+    lbfgsb::router::lbfgsb(&mut problem, &LbfgsbParameter::default())?;
+
+    for (x, c) in problem.x.iter().zip(&center) {
+        assert!((x - c).abs() < 1e-6);
+    }
+    assert_eq!(problem.aux, Some(problem.eval_fn.neval));
+
+    Ok(())
+}
+
+#[test]
+fn test_check_gradient() -> Result<()> {
+    let mut objective = Quadratic { center: vec![1.0, 2.0], neval: 0 };
+    let err = check_gradient(&mut objective, &[0.5, -0.5], 1e-6)?;
+    assert!(err < 1e-6);
+
+    // a wrong gradient is caught as well
+    let mut wrong = |x: &[f64], g: &mut [f64]| -> Result<f64> {
+        g[0] = x[0];
+        Ok(x[0] * x[0])
+    };
+    let err = check_gradient(&mut wrong, &[1.0], 1e-6)?;
+    assert!(err > 0.5);
+
+    Ok(())
+}
+// objective.rs:1 ends here
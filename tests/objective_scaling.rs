@@ -0,0 +1,96 @@
+// [[file:../lbfgsb.note::*objective_scaling.rs][objective_scaling.rs:1]]
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use lbfgsb::scaling::{ObjectiveScaling, Scaling};
+use lbfgsb::shared::{LbfgsbParameter, LbfgsbProblem};
+
+// c * rosenbrock(x) + offset
+fn rosenbrock(c: f64, offset: f64) -> impl FnMut(&[f64], &mut [f64]) -> Result<f64> {
+    move |x: &[f64], g: &mut [f64]| {
+        let (a, b) = (1.0 - x[0], x[1] - x[0] * x[0]);
+        g[0] = c * (-2.0 * a - 400.0 * x[0] * b);
+        g[1] = c * 200.0 * b;
+        Ok(c * (a * a + 100.0 * b * b) + offset)
+    }
+}
+
+// the final x, f and g
+struct Solution {
+    x: Vec<f64>,
+    f: f64,
+    g: Vec<f64>,
+}
+
+fn solve(c: f64, offset: f64, scaling: Option<ObjectiveScaling>) -> Result<Solution> {
+    let mut problem = LbfgsbProblem::build(vec![-1.2, 1.0], rosenbrock(c, offset));
+    problem.set_bounds(vec![(Some(-2.0), Some(2.0)); 2]);
+    problem.objective_scaling = scaling;
+    lbfgsb::router::lbfgsb(&mut problem, &LbfgsbParameter::default())?;
+    Ok(Solution { x: problem.x, f: problem.f, g: problem.g })
+}
+
+#[test]
+fn test_first_evaluation() -> Result<()> {
+    // the tiny f and g pass the factr and pgtol tests at once
+    let plain = solve(1e-12, 0.0, None)?;
+    assert!((plain.x[0] - 1.0).abs() > 1e-2);
+
+    let problem = solve(1e-12, 0.0, Some(ObjectiveScaling::FirstEvaluation))?;
+    assert!((problem.x[0] - 1.0).abs() < 1e-3);
+    assert!((problem.x[1] - 1.0).abs() < 1e-3);
+    // f and g are reported unscaled, exactly
+    let mut g = vec![0.0; 2];
+    let f = rosenbrock(1e-12, 0.0)(&problem.x, &mut g)?;
+    assert_eq!(problem.f, f);
+    assert_eq!(problem.g, g);
+
+    Ok(())
+}
+
+#[test]
+fn test_fixed_offset() -> Result<()> {
+    // setulb sees f - 1e6, while f is reported as evaluated
+    let scaling = ObjectiveScaling::Fixed { factor: 0.5, offset: 1e6 };
+    let problem = solve(1.0, 1e6, Some(scaling))?;
+    assert!((problem.x[0] - 1.0).abs() < 1e-4);
+    assert!((problem.x[1] - 1.0).abs() < 1e-4);
+    assert!((problem.f - 1e6).abs() < 1e-6);
+    let mut g = vec![0.0; 2];
+    rosenbrock(1.0, 1e6)(&problem.x, &mut g)?;
+    assert_eq!(problem.g, g);
+
+    let invalid = ObjectiveScaling::Fixed { factor: -1.0, offset: 0.0 };
+    assert!(solve(1.0, 0.0, Some(invalid)).is_err());
+    // with variable scaling too, the problem is left as it was
+    let mut problem = LbfgsbProblem::build(vec![-1.2, 1.0], rosenbrock(1.0, 0.0));
+    problem.set_bounds(vec![(Some(-2.0), Some(2.0)); 2]);
+    problem.scaling = Some(Scaling::Factors(vec![2.0, 4.0]));
+    problem.objective_scaling = Some(invalid);
+    assert!(lbfgsb::router::lbfgsb(&mut problem, &LbfgsbParameter::default()).is_err());
+    assert_eq!(problem.x, vec![-1.2, 1.0]);
+    assert_eq!((problem.l.clone(), problem.u.clone()), (vec![-2.0; 2], vec![2.0; 2]));
+
+    Ok(())
+}
+
+#[test]
+fn test_observer_units() -> Result<()> {
+    let seen = Arc::new(Mutex::new(vec![]));
+    let s = seen.clone();
+    let mut problem = LbfgsbProblem::build(vec![-1.2, 1.0], rosenbrock(1e-12, 0.0));
+    problem.set_bounds(vec![(None, None); 2]);
+    problem.objective_scaling = Some(ObjectiveScaling::FirstEvaluation);
+    problem.observer = Some(Box::new(move |progress| {
+        s.lock().unwrap().push((progress.f, progress.g[1]));
+        Ok(true)
+    }));
+    lbfgsb::router::lbfgsb(&mut problem, &LbfgsbParameter::default())?;
+
+    let seen = seen.lock().unwrap();
+    assert!(seen.len() > 10);
+    assert!(seen.iter().all(|(f, g)| *f < 1e-10 && g.abs() < 1e-8));
+
+    Ok(())
+}
+// objective_scaling.rs:1 ends here
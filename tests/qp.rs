@@ -0,0 +1,53 @@
+// [[file:../lbfgsb.note::*qp.rs][qp.rs:1]]
+use anyhow::Result;
+use lbfgsb::operator::{DenseMatrix, LinearOperator};
+use lbfgsb::qp::solve_qp;
+use lbfgsb::shared::LbfgsbParameter;
+
+// the covariance of a small portfolio
+fn covariance(n: usize) -> Result<DenseMatrix> {
+    let data = (0..n * n)
+        .map(|k| {
+            let (i, j) = (k / n, k % n);
+            let (si, sj) = (0.1 + 0.02 * i as f64, 0.1 + 0.02 * j as f64);
+            if i == j { si * sj } else { 0.3 * si * sj }
+        })
+        .collect();
+    DenseMatrix::new(n, n, data)
+}
+
+#[test]
+fn test_qp() -> Result<()> {
+    let n = 20;
+    let q = covariance(n)?;
+    let param = LbfgsbParameter { factr: 0.0, pgtol: 1e-10, ..Default::default() };
+    let bounds = vec![(Some(0.0), Some(0.02)); n];
+
+    // 1/2 x'Qx - mu'x with expected returns mu
+    let c: Vec<f64> = (0..n).map(|i| -0.001 * (i as f64 - 5.0)).collect();
+    let sol = solve_qp(&q, &c, &bounds, None, &param)?;
+    assert!(sol.kkt.residual < 1e-9);
+    assert!(!sol.result.at_lower.is_empty());
+    assert!(!sol.result.at_upper.is_empty());
+    // f and g are exact at the solution
+    let mut qx = vec![0.0; n];
+    q.apply(&sol.x, &mut qx);
+    for i in 0..n {
+        assert_eq!(sol.g[i], qx[i] + c[i]);
+    }
+
+    // a slightly different program, warm started from the solution
+    let c2: Vec<f64> = c.iter().map(|ci| ci * 1.01).collect();
+    let cold = solve_qp(&q, &c2, &bounds, None, &param)?;
+    let warm = solve_qp(&q, &c2, &bounds, Some(sol.warm_start()), &param)?;
+    assert!(warm.kkt.residual < 1e-9);
+    assert!(warm.result.nfg < cold.result.nfg);
+    for i in 0..n {
+        assert!((warm.x[i] - cold.x[i]).abs() < 1e-8);
+    }
+
+    assert!(solve_qp(&q, &c[..3], &bounds, None, &param).is_err());
+
+    Ok(())
+}
+// qp.rs:1 ends here
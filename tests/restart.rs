@@ -0,0 +1,97 @@
+// [[file:../lbfgsb.note::*restart.rs][restart.rs:1]]
+use std::cell::Cell;
+use std::rc::Rc;
+
+use anyhow::Result;
+use lbfgsb::shared::task::ABNORMAL;
+use lbfgsb::shared::{LbfgsbParameter, LbfgsbProblem, LbfgsbResult, RestartPolicy, StopReason};
+use lbfgsb::stopping::StopCriteria;
+
+// An ill-conditioned quadratic with its minimum at x = 1, where the
+// observer spoils the evaluations after the third iterate. setulb retries
+// a failed line search once with cleared memory itself, so 2 maxls spoiled
+// evaluations end the run with ABNORMAL. Returns the summary and final f.
+fn solve(param: &LbfgsbParameter) -> Result<(LbfgsbResult, f64)> {
+    let spoiled = Rc::new(Cell::new(0));
+    let s = spoiled.clone();
+    let evaluate = move |x: &[f64], g: &mut [f64]| {
+        let mut f = 0.0;
+        for i in 0..x.len() {
+            let w = 1.0 + (i * i) as f64;
+            f += w * (x[i] - 1.0).powi(2);
+            g[i] = 2.0 * w * (x[i] - 1.0);
+        }
+        if s.get() > 0 {
+            s.set(s.get() - 1);
+            return Ok(1e10);
+        }
+        Ok(f)
+    };
+    let mut problem = LbfgsbProblem::build(vec![3.0; 10], evaluate);
+    problem.set_bounds(vec![(None, None); 10]);
+    let maxls = param.maxls;
+    problem.observer = Some(Box::new(move |progress| {
+        if progress.niter == 3 {
+            spoiled.set(2 * maxls);
+        }
+        Ok(true)
+    }));
+    let result = lbfgsb::router::lbfgsb(&mut problem, param)?;
+    Ok((result, problem.f))
+}
+
+#[test]
+fn test_restart() -> Result<()> {
+    let mut param = LbfgsbParameter { maxls: 1, ..Default::default() };
+    let (once, f_once) = solve(&param)?;
+    assert_eq!(once.task, ABNORMAL as i64);
+    assert_eq!(once.nrestart, 0);
+
+    // the restarted run gets past the failed line search
+    param.restart = RestartPolicy { max_restarts: 2, m: Some(1) };
+    let (restarted, f) = solve(&param)?;
+    assert!(restarted.nrestart > 0);
+    assert!(restarted.niter > once.niter);
+    assert!(f < 1e-2 * f_once);
+
+    // a restart needs at least one correction
+    param.restart.m = Some(0);
+    assert!(solve(&param).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_restart_stagnation() -> Result<()> {
+    // 1e-3 x^4, where the observer spoils the first trial point after every
+    // second iterate, so that the line search fails and setulb restarts
+    let spoil = Rc::new(Cell::new(false));
+    let s = spoil.clone();
+    let evaluate = move |x: &[f64], g: &mut [f64]| {
+        g[0] = 4e-3 * x[0].powi(3);
+        Ok(if s.replace(false) { 1e10 } else { 1e-3 * x[0].powi(4) })
+    };
+    // f decreases by less than 1 over any 3 iterations
+    let stop = StopCriteria { stagnation_tol: Some(1.0), stagnation_iters: 3, ..Default::default() };
+    let param = LbfgsbParameter {
+        factr: 0.0,
+        pgtol: 0.0,
+        maxls: 1,
+        restart: RestartPolicy { max_restarts: 10, m: None },
+        stop,
+        ..Default::default()
+    };
+    let mut problem = LbfgsbProblem::build(vec![3.3], evaluate);
+    problem.set_bounds(vec![(None, None)]);
+    problem.observer = Some(Box::new(move |progress| {
+        spoil.set(progress.niter % 2 == 0);
+        Ok(true)
+    }));
+    let result = lbfgsb::router::lbfgsb(&mut problem, &param)?;
+    // the history carries over the restart after the second iterate
+    assert_eq!(result.stop, Some(StopReason::Stagnation));
+    assert_eq!(result.nrestart, 1);
+
+    Ok(())
+}
+// restart.rs:1 ends here
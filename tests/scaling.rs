@@ -0,0 +1,70 @@
+// [[file:../lbfgsb.note::*scaling.rs][scaling.rs:1]]
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use lbfgsb::scaling::Scaling;
+use lbfgsb::shared::{LbfgsbParameter, LbfgsbProblem, LbfgsbResult};
+
+// sum of (x_i / c_i - 1)^2 with c_i from 1e-6 to 1e6
+fn solve(scaling: Option<Scaling>) -> Result<(LbfgsbResult, Vec<f64>, Vec<f64>)> {
+    let c: Vec<f64> = (0..7).map(|i| 10f64.powi(2 * i - 6)).collect();
+    let c2 = c.clone();
+    let evaluate = move |x: &[f64], g: &mut [f64]| {
+        let mut f = 0.0;
+        for i in 0..x.len() {
+            let d = x[i] / c2[i] - 1.0;
+            f += d * d;
+            g[i] = 2.0 * d / c2[i];
+        }
+        Ok(f)
+    };
+    let mut problem = LbfgsbProblem::build(c.iter().map(|ci| 0.1 * ci).collect(), evaluate);
+    problem.set_bounds(c.iter().map(|ci| (Some(0.0), Some(10.0 * ci))));
+    problem.scaling = scaling;
+    let param = LbfgsbParameter { factr: 0.0, pgtol: 1e-12, ..Default::default() };
+    let result = lbfgsb::router::lbfgsb(&mut problem, &param)?;
+    Ok((result, problem.x, problem.u))
+}
+
+#[test]
+fn test_variable_scaling() -> Result<()> {
+    let c: Vec<f64> = (0..7).map(|i| 10f64.powi(2 * i - 6)).collect();
+    let (plain, _, _) = solve(None)?;
+
+    for scaling in [Scaling::Factors(c.clone()), Scaling::Bounds, Scaling::Gradient] {
+        let (result, x, u) = solve(Some(scaling))?;
+        assert!(result.niter < plain.niter);
+        // in original units
+        for (i, ci) in c.iter().enumerate() {
+            assert!((x[i] / ci - 1.0).abs() < 1e-6);
+            assert_eq!(u[i], 10.0 * ci);
+        }
+    }
+
+    assert!(solve(Some(Scaling::Factors(vec![1.0; 3]))).is_err());
+    assert!(solve(Some(Scaling::Factors(vec![0.0; 7]))).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_observer_units() -> Result<()> {
+    let seen = Arc::new(Mutex::new(vec![]));
+    let s = seen.clone();
+    let mut problem = LbfgsbProblem::build(vec![1e5], |x: &[f64], g: &mut [f64]| {
+        g[0] = 2.0 * (x[0] - 3e6);
+        Ok((x[0] - 3e6).powi(2))
+    });
+    problem.set_bounds(vec![(None, None)]);
+    problem.scaling = Some(Scaling::Factors(vec![1e6]));
+    problem.observer = Some(Box::new(move |progress| {
+        s.lock().unwrap().push(progress.x[0]);
+        Ok(true)
+    }));
+    lbfgsb::router::lbfgsb(&mut problem, &LbfgsbParameter::default())?;
+    assert!((seen.lock().unwrap().last().unwrap() - 3e6).abs() < 1.0);
+    assert!((problem.x[0] - 3e6).abs() < 1.0);
+
+    Ok(())
+}
+// scaling.rs:1 ends here
@@ -0,0 +1,65 @@
+// [[file:../lbfgsb.note::*select.rs][select.rs:1]]
+use anyhow::Result;
+use lbfgsb::shared::{LbfgsbParameter, LbfgsbProblem};
+use lbfgsb::subset::Selection;
+
+// sum of (x_i - x_{i+1})^2 + (x_0 - 1)^2
+fn evaluate(x: &[f64], g: &mut [f64]) -> Result<f64> {
+    assert_eq!(x.len(), 4);
+    let mut f = (x[0] - 1.0).powi(2);
+    g.fill(0.0);
+    g[0] = 2.0 * (x[0] - 1.0);
+    for i in 0..3 {
+        let d = x[i] - x[i + 1];
+        f += d * d;
+        g[i] += 2.0 * d;
+        g[i + 1] -= 2.0 * d;
+    }
+    Ok(f)
+}
+
+type Evaluate = fn(&[f64], &mut [f64]) -> Result<f64>;
+
+fn solve(select: Selection) -> Result<LbfgsbProblem<Evaluate>> {
+    let mut problem = LbfgsbProblem::build(vec![0.0, 5.0, 0.0, 3.0], evaluate as Evaluate);
+    problem.set_bounds(vec![(None, None); 4]);
+    problem.select = Some(select);
+    lbfgsb::router::lbfgsb(&mut problem, &LbfgsbParameter::default())?;
+    Ok(problem)
+}
+
+#[test]
+fn test_select() -> Result<()> {
+    for select in [Selection::Indices(vec![0, 2]), Selection::Mask(vec![true, false, true, false])] {
+        let problem = solve(select)?;
+        let x = &problem.x;
+        // x_1 and x_3 stay, x_0 = (1 + 5) / 2 and x_2 = (5 + 3) / 2
+        assert_eq!((x[1], x[3]), (5.0, 3.0));
+        assert!((x[0] - 3.0).abs() < 1e-5);
+        assert!((x[2] - 4.0).abs() < 1e-5);
+        // the full gradient at the solution
+        assert!(problem.g[0].abs() < 1e-5 && problem.g[2].abs() < 1e-5);
+        assert!((problem.g[1] - 2.0 * ((x[1] - x[0]) + (x[1] - x[2]))).abs() < 1e-12);
+    }
+
+    assert!(solve(Selection::Indices(vec![4])).is_err());
+    assert!(solve(Selection::Mask(vec![true; 3])).is_err());
+    assert!(solve(Selection::Mask(vec![false; 4])).is_err());
+
+    Ok(())
+}
+#[test]
+fn test_select_fixed() -> Result<()> {
+    // with fixed variables eliminated, selecting only x_1 = 2 is a no-op
+    let mut problem = LbfgsbProblem::build(vec![0.0, 5.0, 0.0, 3.0], evaluate as Evaluate);
+    problem.set_bounds(vec![(None, None), (Some(2.0), Some(2.0)), (None, None), (None, None)]);
+    problem.select = Some(Selection::Indices(vec![1]));
+    let param = LbfgsbParameter { eliminate_fixed: true, ..Default::default() };
+    let result = lbfgsb::router::lbfgsb(&mut problem, &param)?;
+    assert_eq!(problem.x, vec![0.0, 2.0, 0.0, 3.0]);
+    assert_eq!((result.niter, result.nfg), (0, 0));
+    assert!(result.at_lower.is_empty() && result.at_upper.is_empty());
+
+    Ok(())
+}
+// select.rs:1 ends here
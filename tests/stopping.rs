@@ -0,0 +1,45 @@
+// [[file:../lbfgsb.note::*stopping.rs][stopping.rs:1]]
+use anyhow::Result;
+use lbfgsb::shared::{LbfgsbParameter, LbfgsbProblem, LbfgsbResult, StopReason};
+use lbfgsb::stopping::StopCriteria;
+
+fn solve(stop: StopCriteria) -> Result<(LbfgsbResult, f64)> {
+    let evaluate = |x: &[f64], g: &mut [f64]| {
+        let mut f = 0.0;
+        for i in 0..x.len() {
+            let w = (i + 1) as f64;
+            g[i] = w * (x[i] - 1.0);
+            f += 0.5 * w * (x[i] - 1.0).powi(2);
+        }
+        Ok(f)
+    };
+    let mut problem = LbfgsbProblem::build(vec![-10.0; 8], evaluate);
+    problem.set_bounds(vec![(None, None); 8]);
+    let param = LbfgsbParameter { stop, pgtol: 0.0, factr: 0.0, ..Default::default() };
+    let result = lbfgsb::router::lbfgsb(&mut problem, &param)?;
+    Ok((result, problem.f))
+}
+
+#[test]
+fn test_stop_criteria() -> Result<()> {
+    let (full, _) = solve(StopCriteria::default())?;
+    assert_eq!(full.stop, None);
+
+    let (result, f) = solve(StopCriteria { f_target: Some(1e-2), ..Default::default() })?;
+    assert_eq!(result.stop, Some(StopReason::TargetF));
+    assert!(f <= 1e-2);
+    assert!(result.niter < full.niter);
+
+    let stop = StopCriteria { x_tol: Some(1e3), x_iters: 2, ..Default::default() };
+    let (result, _) = solve(stop)?;
+    assert_eq!(result.stop, Some(StopReason::XChange));
+    assert_eq!(result.niter, 2);
+
+    let stop = StopCriteria { stagnation_tol: Some(1e10), stagnation_iters: 3, ..Default::default() };
+    let (result, _) = solve(stop)?;
+    assert_eq!(result.stop, Some(StopReason::Stagnation));
+    assert_eq!(result.niter, 3);
+
+    Ok(())
+}
+// stopping.rs:1 ends here
@@ -0,0 +1,26 @@
+// [[file:../lbfgsb.note::*timing.rs][timing.rs:1]]
+use std::time::Duration;
+
+use anyhow::Result;
+use lbfgsb::shared::{LbfgsbParameter, LbfgsbProblem};
+
+#[test]
+fn test_timing() -> Result<()> {
+    let evaluate = |x: &[f64], g: &mut [f64]| {
+        std::thread::sleep(Duration::from_millis(1));
+        g[0] = 2.0 * (x[0] - 3.0);
+        g[1] = 2.0 * (x[1] + 1.0);
+        Ok((x[0] - 3.0).powi(2) + (x[1] + 1.0).powi(2))
+    };
+    let mut problem = LbfgsbProblem::build(vec![0.0, 0.0], evaluate);
+    problem.set_bounds(vec![(None, None), (Some(0.0), None)]);
+    let result = lbfgsb::router::lbfgsb(&mut problem, &LbfgsbParameter::default())?;
+
+    let timing = result.timing;
+    assert!(timing.objective >= Duration::from_millis(result.nfg as u64));
+    assert!(timing.solver > Duration::ZERO);
+    assert!(timing.solver < timing.objective);
+
+    Ok(())
+}
+// timing.rs:1 ends here
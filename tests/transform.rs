@@ -0,0 +1,95 @@
+// [[file:../lbfgsb.note::*transform.rs][transform.rs:1]]
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use lbfgsb::shared::{LbfgsbParameter, LbfgsbProblem};
+use lbfgsb::transform::Transform;
+
+// a rate k > 0 spanning decades and a fraction p in (0, 1):
+// (ln k - ln 2e-4)^2 + (p - 0.9)^2
+fn evaluate(x: &[f64], g: &mut [f64]) -> Result<f64> {
+    assert!(x[0] > 0.0 && x[1] > 0.0 && x[1] < 1.0);
+    let d = x[0].ln() - 2e-4f64.ln();
+    g[0] = 2.0 * d / x[0];
+    g[1] = 2.0 * (x[1] - 0.9);
+    Ok(d * d + (x[1] - 0.9).powi(2))
+}
+
+#[test]
+fn test_transforms() -> Result<()> {
+    let seen = Arc::new(Mutex::new(vec![]));
+    let s = seen.clone();
+    let mut problem = LbfgsbProblem::build(vec![1.0, 0.5], evaluate);
+    problem.set_bounds(vec![(Some(0.0), None), (Some(0.0), Some(1.0))]);
+    problem.transforms = Some(vec![Transform::Log, Transform::Logit]);
+    problem.observer = Some(Box::new(move |progress| {
+        s.lock().unwrap().push(progress.x[0]);
+        Ok(true)
+    }));
+    lbfgsb::router::lbfgsb(&mut problem, &LbfgsbParameter::default())?;
+    // natural units
+    assert!((problem.x[0] / 2e-4 - 1.0).abs() < 1e-4);
+    assert!((problem.x[1] - 0.9).abs() < 1e-4);
+    assert!(problem.g[1].abs() < 1e-4);
+    assert!(seen.lock().unwrap().iter().all(|k| *k > 0.0));
+    assert!(problem.observer.is_some());
+    assert_eq!(problem.l, vec![0.0, 0.0]);
+
+    Ok(())
+}
+
+#[test]
+fn test_bound_mapping() -> Result<()> {
+    // the minimum at x = -1 is beyond the bound x >= 2, seen by setulb
+    // as y <= (2 - 4) / -2 = 1
+    let mut problem = LbfgsbProblem::build(vec![3.0], |x: &[f64], g: &mut [f64]| {
+        g[0] = 2.0 * (x[0] + 1.0);
+        Ok((x[0] + 1.0).powi(2))
+    });
+    problem.set_bounds(vec![(Some(2.0), None)]);
+    problem.transforms = Some(vec![Transform::Affine { scale: -2.0, offset: 4.0 }]);
+    let result = lbfgsb::router::lbfgsb(&mut problem, &LbfgsbParameter::default())?;
+    assert_eq!(problem.x[0], 2.0);
+    assert_eq!(problem.g[0], 6.0);
+    assert_eq!(result.at_lower, vec![0]);
+
+    // log of a non-positive value, logit without bounds
+    let mut problem = LbfgsbProblem::build(vec![-1.0], evaluate);
+    problem.transforms = Some(vec![Transform::Log]);
+    assert!(lbfgsb::router::lbfgsb(&mut problem, &LbfgsbParameter::default()).is_err());
+    problem.transforms = Some(vec![Transform::Logit]);
+    assert!(lbfgsb::router::lbfgsb(&mut problem, &LbfgsbParameter::default()).is_err());
+
+    Ok(())
+}
+#[test]
+fn test_saturated_logit() -> Result<()> {
+    // the minimum of (x - 2)^2 within [0, 1] is at the upper bound, which
+    // the logit transform approaches until dx/dy vanishes
+    let seen = Arc::new(Mutex::new(vec![]));
+    let s = seen.clone();
+    let mut problem = LbfgsbProblem::build(vec![0.5], |x: &[f64], g: &mut [f64]| {
+        g[0] = 2.0 * (x[0] - 2.0);
+        Ok((x[0] - 2.0).powi(2))
+    });
+    problem.set_bounds(vec![(Some(0.0), Some(1.0))]);
+    problem.transforms = Some(vec![Transform::Logit]);
+    problem.observer = Some(Box::new(move |progress| {
+        s.lock().unwrap().push((progress.x[0], progress.g[0], progress.pgnorm));
+        Ok(true)
+    }));
+    let param = LbfgsbParameter { factr: 0.0, pgtol: 0.0, return_best: true, ..Default::default() };
+    let result = lbfgsb::router::lbfgsb(&mut problem, &param)?;
+    assert_eq!(problem.x[0], 1.0);
+    assert_eq!(problem.g[0], -2.0);
+    let best = result.best.unwrap();
+    assert_eq!(best.g, vec![2.0 * (best.x[0] - 2.0)]);
+    for (x, g, pgnorm) in seen.lock().unwrap().iter() {
+        assert_eq!(*g, 2.0 * (x - 2.0));
+        // natural units: g < 0 pushes x to its upper bound
+        assert_eq!(*pgnorm, (1.0 - x).min(-g));
+    }
+
+    Ok(())
+}
+// transform.rs:1 ends here
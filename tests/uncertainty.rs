@@ -0,0 +1,68 @@
+// [[file:../lbfgsb.note::*uncertainty.rs][uncertainty.rs:1]]
+use anyhow::Result;
+use lbfgsb::scaling::Scaling;
+use lbfgsb::shared::{LbfgsbParameter, LbfgsbProblem};
+use lbfgsb::transform::Transform;
+use lbfgsb::uncertainty::{estimate, HessianSource};
+
+// f = 0.5 x'Ax - b'x with the minimum at x = (1, 2, -1), the last variable
+// bounded below by 0.
+const A: [[f64; 3]; 3] = [[4.0, 1.0, 0.0], [1.0, 3.0, 0.5], [0.0, 0.5, 2.0]];
+
+fn evaluate(x: &[f64], g: &mut [f64]) -> Result<f64> {
+    let c = [1.0, 2.0, -1.0];
+    let mut f = 0.0;
+    for i in 0..3 {
+        g[i] = (0..3).map(|j| A[i][j] * (x[j] - c[j])).sum();
+        f += 0.5 * (x[i] - c[i]) * g[i];
+    }
+    Ok(f)
+}
+
+#[test]
+fn test_uncertainty() -> Result<()> {
+    let mut problem = LbfgsbProblem::build(vec![0.5; 3], evaluate);
+    problem.set_bounds(vec![(None, None), (None, None), (Some(0.0), None)]);
+    let param = LbfgsbParameter { m: 10, pgtol: 1e-10, ..Default::default() };
+    let result = lbfgsb::router::lbfgsb(&mut problem, &param)?;
+    assert_eq!(problem.x[2], 0.0);
+
+    // the inverse of the leading 2x2 block of A
+    let det = A[0][0] * A[1][1] - A[0][1] * A[1][0];
+    let expected = [[A[1][1] / det, -A[0][1] / det], [-A[1][0] / det, A[0][0] / det]];
+
+    let fd = estimate(&mut problem, &result, HessianSource::FiniteDifference(1e-5))?;
+    assert_eq!(fd.free, vec![0, 1]);
+    assert_eq!(fd.std_errors[2], None);
+    for (row, expected) in fd.covariance.iter().zip(&expected) {
+        for (cij, eij) in row.iter().zip(expected) {
+            assert!((cij - eij).abs() < 1e-6);
+        }
+    }
+    assert!((fd.std_errors[0].unwrap() - expected[0][0].sqrt()).abs() < 1e-6);
+
+    let memory = estimate(&mut problem, &result, HessianSource::Memory)?;
+    assert_eq!(memory.free, vec![0, 1]);
+    assert!(memory.std_errors[0].unwrap() > 0.0);
+    assert!(memory.std_errors[1].unwrap() > 0.0);
+
+    // the memory of a scaled run gives the covariance in x
+    let mut scaled = LbfgsbProblem::build(vec![0.5; 3], evaluate);
+    scaled.set_bounds(vec![(None, None), (None, None), (Some(0.0), None)]);
+    scaled.scaling = Some(Scaling::Factors(vec![1e3, 1.0, 1.0]));
+    let result = lbfgsb::router::lbfgsb(&mut scaled, &param)?;
+    let from_scaled = estimate(&mut scaled, &result, HessianSource::Memory)?;
+    let ratio = from_scaled.std_errors[0].unwrap() / expected[0][0].sqrt();
+    assert!(ratio > 0.5 && ratio < 2.0, "{ratio}");
+
+    // the memory of a transformed run is in other variables
+    let mut transformed = LbfgsbProblem::build(vec![0.5; 3], evaluate);
+    transformed.set_bounds(vec![(None, None), (None, None), (Some(0.0), None)]);
+    transformed.transforms = Some(vec![Transform::Affine { scale: 10.0, offset: 0.0 }, Transform::Identity, Transform::Identity]);
+    let result = lbfgsb::router::lbfgsb(&mut transformed, &param)?;
+    assert!(estimate(&mut transformed, &result, HessianSource::Memory).is_err());
+    assert!(estimate(&mut transformed, &result, HessianSource::FiniteDifference(1e-5)).is_ok());
+
+    Ok(())
+}
+// uncertainty.rs:1 ends here
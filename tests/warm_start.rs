@@ -0,0 +1,112 @@
+// [[file:../lbfgsb.note::*warm_start.rs][warm_start.rs:1]]
+use anyhow::Result;
+use lbfgsb::memory::CorrectionPairs;
+use lbfgsb::scaling::Scaling;
+use lbfgsb::shared::{LbfgsbParameter, LbfgsbProblem, LbfgsbResult};
+
+/// Minimize an ill-conditioned quadratic centered at `c`, optionally with
+/// every other variable bounded away from its unconstrained minimum.
+fn solve(c: &[f64], bounded: bool, warm: Option<&LbfgsbResult>) -> Result<LbfgsbResult> {
+    let n = c.len();
+    let evaluate = |x: &[f64], g: &mut [f64]| {
+        let mut f = 0.0;
+        for i in 0..n {
+            let w = 1.0 + (i * i) as f64;
+            let d = x[i] - c[i];
+            f += 0.5 * w * d * d;
+            g[i] = w * d;
+        }
+        Ok(f)
+    };
+    let mut problem = LbfgsbProblem::build(vec![0.0; n], evaluate);
+    let bound = |i: usize| if bounded && i.is_multiple_of(2) { (Some(0.0), Some(0.5)) } else { (None, None) };
+    problem.set_bounds((0..n).map(bound));
+    problem.warm_start = warm.map(|r| r.memory.clone());
+    let param = LbfgsbParameter { m: 10, pgtol: 1e-8, ..Default::default() };
+    lbfgsb::router::lbfgsb(&mut problem, &param)
+}
+
+#[test]
+fn test_warm_start() -> Result<()> {
+    let c: Vec<f64> = (0..5).map(|i| 1.0 + 0.1 * i as f64).collect();
+    // a slightly perturbed problem
+    let c2: Vec<f64> = c.iter().map(|ci| ci * 1.01).collect();
+
+    for bounded in [false, true] {
+        let first = solve(&c, bounded, None)?;
+        assert!(!first.memory.is_empty());
+        assert!(first.memory.theta > 0.0);
+
+        let cold = solve(&c2, bounded, None)?;
+        let warm = solve(&c2, bounded, Some(&first))?;
+        assert!(warm.niter < cold.niter);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_warm_start_active_bounds() -> Result<()> {
+    // pairs from the unconstrained problem seed a run that starts at the
+    // upper bounds and ends with a different set of them active
+    let n = 8;
+    let c: Vec<f64> = (0..n).map(|i| 1.0 + 0.1 * i as f64).collect();
+    let free = solve(&c, false, None)?;
+    let run = |warm: Option<&LbfgsbResult>| -> Result<(LbfgsbResult, Vec<f64>)> {
+        let evaluate = |x: &[f64], g: &mut [f64]| {
+            let mut f = 0.0;
+            for i in 0..n {
+                let w = 1.0 + (i * i) as f64;
+                let d = x[i] - c[i];
+                f += 0.5 * w * d * d;
+                g[i] = w * d;
+            }
+            Ok(f)
+        };
+        let mut problem = LbfgsbProblem::build(vec![1.2; n], evaluate);
+        problem.set_bounds((0..n).map(|_| (Some(0.0), Some(1.2))));
+        problem.warm_start = warm.map(|r| r.memory.clone());
+        let param = LbfgsbParameter { m: 10, pgtol: 1e-10, ..Default::default() };
+        let result = lbfgsb::router::lbfgsb(&mut problem, &param)?;
+        Ok((result, problem.x))
+    };
+    let (cold, x_cold) = run(None)?;
+    let (warm, x_warm) = run(Some(&free))?;
+    assert!(!cold.at_upper.is_empty());
+    assert_eq!(warm.at_upper, cold.at_upper);
+    assert_eq!(warm.at_lower, cold.at_lower);
+    for i in 0..n {
+        assert!((x_warm[i] - x_cold[i]).abs() < 1e-8, "{i}: {} {}", x_warm[i], x_cold[i]);
+        assert!((x_cold[i] - c[i].min(1.2)).abs() < 1e-8);
+    }
+    assert!(warm.niter <= cold.niter);
+
+    Ok(())
+}
+
+#[test]
+fn test_warm_start_mismatched() -> Result<()> {
+    let evaluate = |x: &[f64], g: &mut [f64]| {
+        g.iter_mut().zip(x).for_each(|(gi, xi)| *gi = xi - 1.0);
+        Ok(x.iter().map(|xi| 0.5 * (xi - 1.0) * (xi - 1.0)).sum())
+    };
+    let pairs = |k: usize, scale: Option<Vec<f64>>| CorrectionPairs {
+        s: vec![vec![1.0; k], vec![1.0; 4]],
+        y: vec![vec![1.0; k], vec![1.0; 4]],
+        theta: 1.0,
+        scale,
+    };
+    // pairs and scale vectors not of length n under variable scaling are
+    // skipped instead of panicking
+    for warm in [pairs(3, None), pairs(3, Some(vec![2.0; 3])), pairs(4, Some(vec![2.0; 3])), pairs(4, Some(vec![2.0; 4]))] {
+        let mut problem = LbfgsbProblem::build(vec![0.0; 4], evaluate);
+        problem.set_bounds(vec![(None, None); 4]);
+        problem.scaling = Some(Scaling::Factors(vec![1.0, 2.0, 3.0, 4.0]));
+        problem.warm_start = Some(warm);
+        lbfgsb::router::lbfgsb(&mut problem, &LbfgsbParameter::default())?;
+        assert!(problem.x.iter().all(|xi| (xi - 1.0).abs() < 1e-4));
+    }
+
+    Ok(())
+}
+// warm_start.rs:1 ends here